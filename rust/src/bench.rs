@@ -0,0 +1,266 @@
+use crate::error::AocError;
+use crate::solver::Solver;
+use crate::utils::{Dataset, Part};
+use std::collections::HashMap;
+use std::fs;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+pub struct Samples {
+    pub parse: Vec<Duration>,
+    pub solve: Vec<Duration>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Stats {
+    pub min: f64,
+    pub median: f64,
+    pub p95: f64,
+    pub std_dev: f64,
+}
+
+pub struct BenchResult {
+    pub day: u8,
+    pub solver: &'static str,
+    pub part: Part,
+    pub dataset: Dataset,
+    pub parse: Stats,
+    pub solve: Stats,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Regression {
+    pub phase: &'static str,
+    pub baseline: f64,
+    pub current: f64,
+}
+
+/// Baseline medians in µs, keyed by (solver, part, dataset, phase).
+pub type Baseline = HashMap<(String, String, String, String), f64>;
+
+/// Durations of `iterations` parses and solves of a part, after `warmup` untimed runs of each.
+pub fn sample<S: Solver>(
+    solver: &S,
+    part: &Part,
+    input: &str,
+    warmup: usize,
+    iterations: usize,
+) -> Result<Samples, AocError> {
+    let mut parse = Vec::with_capacity(iterations);
+    for i in 0..warmup + iterations {
+        let start = Instant::now();
+        black_box(solver.parse(black_box(input))?);
+        if i >= warmup {
+            parse.push(start.elapsed());
+        }
+    }
+
+    let model = solver.parse(input)?;
+    let mut solve = Vec::with_capacity(iterations);
+    for i in 0..warmup + iterations {
+        let start = Instant::now();
+        match part {
+            Part::Part1 => {
+                black_box(solver.part1(black_box(&model))?);
+            }
+            Part::Part2 => {
+                black_box(solver.part2(black_box(&model))?);
+            }
+        }
+        if i >= warmup {
+            solve.push(start.elapsed());
+        }
+    }
+    return Ok(Samples { parse, solve });
+}
+
+fn to_micros(duration: &Duration) -> f64 {
+    duration.as_nanos() as f64 / 1000.0
+}
+
+/// Statistics in µs of the samples, all 0 when there are none.
+pub fn compute_stats(samples: &[Duration]) -> Stats {
+    let mut values: Vec<f64> = samples.iter().map(to_micros).collect();
+    values.sort_by(|a, b| a.total_cmp(b));
+    if values.is_empty() {
+        return Stats {
+            min: 0.0,
+            median: 0.0,
+            p95: 0.0,
+            std_dev: 0.0,
+        };
+    }
+    let len = values.len();
+    let median = if len.is_multiple_of(2) {
+        (values[len / 2 - 1] + values[len / 2]) / 2.0
+    } else {
+        values[len / 2]
+    };
+    // Nearest-rank percentile
+    let p95_rank = ((len as f64) * 0.95).ceil() as usize;
+    let mean = values.iter().sum::<f64>() / len as f64;
+    let variance = values.iter().map(|v| (v - mean) * (v - mean)).sum::<f64>() / len as f64;
+    return Stats {
+        min: values[0],
+        median,
+        p95: values[p95_rank.max(1) - 1],
+        std_dev: variance.sqrt(),
+    };
+}
+
+fn baseline_key(result: &BenchResult, phase: &str) -> (String, String, String, String) {
+    (
+        String::from(result.solver),
+        format!("{:?}", result.part),
+        result.dataset.to_string(),
+        String::from(phase),
+    )
+}
+
+/// Reads one `solver,part,dataset,phase,median_us` line per measure.
+pub fn parse_baseline(content: &str) -> Baseline {
+    return content
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(',').map(|field| field.trim()).collect();
+            if let [solver, part, dataset, phase, median] = fields[..] {
+                let median = median.parse::<f64>().ok()?;
+                return Some((
+                    (
+                        String::from(solver),
+                        String::from(part),
+                        String::from(dataset),
+                        String::from(phase),
+                    ),
+                    median,
+                ));
+            }
+            None
+        })
+        .collect();
+}
+
+pub fn load_baseline(path: &str) -> Result<Baseline, AocError> {
+    return fs::read_to_string(path)
+        .map(|content| parse_baseline(&content))
+        .map_err(|source| AocError::Io {
+            path: String::from(path),
+            source,
+        });
+}
+
+pub fn save_baseline(path: &str, results: &[BenchResult]) -> Result<(), AocError> {
+    let mut content = String::from("solver,part,dataset,phase,median_us\n");
+    for result in results {
+        for (phase, stats) in [("parse", &result.parse), ("solve", &result.solve)] {
+            let (solver, part, dataset, phase) = baseline_key(result, phase);
+            content.push_str(&format!(
+                "{},{},{},{},{:.3}\n",
+                solver, part, dataset, phase, stats.median
+            ));
+        }
+    }
+    return fs::write(path, content).map_err(|source| AocError::Io {
+        path: String::from(path),
+        source,
+    });
+}
+
+/// A phase regresses when its median is slower than the baseline by more than `threshold`
+/// (0.1 = 10%).
+pub fn find_regressions(
+    result: &BenchResult,
+    baseline: &Baseline,
+    threshold: f64,
+) -> Vec<Regression> {
+    return [("parse", &result.parse), ("solve", &result.solve)]
+        .into_iter()
+        .filter_map(|(phase, stats)| {
+            let reference = *baseline.get(&baseline_key(result, phase))?;
+            if stats.median > reference * (1.0 + threshold) {
+                Some(Regression {
+                    phase,
+                    baseline: reference,
+                    current: stats.median,
+                })
+            } else {
+                None
+            }
+        })
+        .collect();
+}
+
+fn format_stats(stats: &Stats) -> String {
+    format!(
+        "min {:.1} µs, median {:.1} µs, p95 {:.1} µs, σ {:.1} µs",
+        stats.min, stats.median, stats.p95, stats.std_dev
+    )
+}
+
+pub fn to_text(result: &BenchResult, regressions: &[Regression]) -> String {
+    let mut line = format!(
+        "[Day {}][{}][{:?}][{}] parse: {} | solve: {}",
+        result.day,
+        result.solver,
+        result.part,
+        result.dataset,
+        format_stats(&result.parse),
+        format_stats(&result.solve)
+    );
+    for regression in regressions {
+        line.push_str(&format!(
+            " REGRESSION {} {:+.0}% ({:.1} µs vs {:.1} µs)",
+            regression.phase,
+            (regression.current / regression.baseline - 1.0) * 100.0,
+            regression.current,
+            regression.baseline
+        ));
+    }
+    return line;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn micros(values: &[u64]) -> Vec<Duration> {
+        values.iter().map(|v| Duration::from_micros(*v)).collect()
+    }
+
+    #[test]
+    fn test_compute_stats() {
+        let stats = compute_stats(&micros(&[5, 1, 4, 2, 3]));
+        assert_eq!(
+            stats,
+            Stats {
+                min: 1.0,
+                median: 3.0,
+                p95: 5.0,
+                std_dev: 2.0_f64.sqrt(),
+            }
+        );
+        assert_eq!(compute_stats(&micros(&[4, 1, 3, 2])).median, 2.5);
+    }
+
+    #[test]
+    fn test_find_regressions() {
+        let result = BenchResult {
+            day: 1,
+            solver: "day1",
+            part: Part::Part1,
+            dataset: Dataset::Real,
+            parse: compute_stats(&micros(&[100])),
+            solve: compute_stats(&micros(&[130])),
+        };
+        let baseline =
+            parse_baseline("solver,part,dataset,phase,median_us\nday1,Part1,Real,parse,95\nday1,Part1,Real,solve,100\n");
+        assert_eq!(
+            find_regressions(&result, &baseline, 0.1),
+            vec![Regression {
+                phase: "solve",
+                baseline: 100.0,
+                current: 130.0,
+            }]
+        );
+    }
+}
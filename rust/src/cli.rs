@@ -0,0 +1,502 @@
+use advent_of_code_2021::report::ReportFormat;
+use advent_of_code_2021::utils::{Dataset, Part};
+use std::fmt;
+
+pub const USAGE: &str = "Usage:
+  advent_of_code_2021 run (--all | --day <N>[,<N>...]) [options]
+  advent_of_code_2021 bench (--all | --day <N>[,<N>...]) [options]
+  advent_of_code_2021 list
+  advent_of_code_2021 help
+
+Common options:
+  --part, -p 1|2|both          parts to solve (default both)
+  --dataset, -s <DS>[,<DS>...] datasets to solve among test, real, both, stdin (or -),
+                               examples (all of data/day_<N>/examples), example:<name>
+                               and <name> for data/day_<N>_<name>.txt
+                               (default test, examples, real)
+  --inline, -i <text>          solve the given text, replaces the default datasets
+  --data-dir <dir>             data root (default $AOC_DATA_DIR, then ./data)
+
+Run options:
+  --format, -f text|json|csv   report format, json is one record per line (default text)
+  --output, -o <file>          write the report to a file instead of stdout
+  --jobs, -j <N>               days and datasets solved in parallel, 0 for one per CPU
+                               (default 1, the report order does not depend on it)
+  --stream                     solve from the input stream, in constant memory, the days
+                               supporting it
+
+Bench options:
+  --iterations, -n <N>         measured iterations per phase (default 20)
+  --warmup, -w <N>             unmeasured iterations before measuring (default 3)
+  --baseline, -b <file>        flag medians slower than this saved baseline
+  --threshold, -t <percent>    tolerated slowdown against the baseline (default 10)
+  --save-baseline <file>       save the measured medians as a new baseline";
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum DaySelection {
+    All,
+    Days(Vec<u8>),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Selection {
+    pub days: DaySelection,
+    pub parts: Vec<Part>,
+    pub datasets: Vec<Dataset>,
+    /// Whether the datasets were given on the command line rather than defaulted
+    pub datasets_given: bool,
+    pub data_dir: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct RunOptions {
+    pub selection: Selection,
+    pub format: ReportFormat,
+    pub output: Option<String>,
+    pub jobs: usize,
+    pub stream: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct BenchOptions {
+    pub selection: Selection,
+    pub iterations: usize,
+    pub warmup: usize,
+    pub baseline: Option<String>,
+    pub threshold: f64,
+    pub save_baseline: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Run(RunOptions),
+    Bench(BenchOptions),
+    List,
+    Help,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CliError {
+    MissingCommand,
+    UnknownCommand(String),
+    UnknownArgument(String),
+    MissingValue(String),
+    BadValue { flag: String, value: String },
+    MissingDaySelection,
+    UnknownDay { day: u8, available: Vec<u8> },
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CliError::MissingCommand => write!(f, "No command given"),
+            CliError::UnknownCommand(cmd) => write!(f, "Unknown command '{}'", cmd),
+            CliError::UnknownArgument(arg) => write!(f, "Unknown argument '{}'", arg),
+            CliError::MissingValue(flag) => write!(f, "Missing value for '{}'", flag),
+            CliError::BadValue { flag, value } => {
+                write!(f, "Bad value '{}' for '{}'", value, flag)
+            }
+            CliError::MissingDaySelection => write!(f, "Either --all or --day must be given"),
+            CliError::UnknownDay { day, available } => write!(
+                f,
+                "Unknown day {} (available: {})",
+                day,
+                available
+                    .iter()
+                    .map(|d| d.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+fn parse_parts(flag: &str, value: &str) -> Result<Vec<Part>, CliError> {
+    return match value {
+        "1" => Ok(vec![Part::Part1]),
+        "2" => Ok(vec![Part::Part2]),
+        "both" => Ok(vec![Part::Part1, Part::Part2]),
+        _ => Err(CliError::BadValue {
+            flag: String::from(flag),
+            value: String::from(value),
+        }),
+    };
+}
+
+fn is_file_name(name: &str) -> bool {
+    return !name.is_empty()
+        && name
+            .chars()
+            .all(|chr| chr.is_ascii_alphanumeric() || chr == '_' || chr == '-');
+}
+
+fn parse_datasets(flag: &str, value: &str) -> Result<Vec<Dataset>, CliError> {
+    let mut datasets = vec![];
+    for name in value.split(',').map(|name| name.trim()) {
+        match name {
+            "test" => datasets.push(Dataset::Test),
+            "real" => datasets.push(Dataset::Real),
+            "both" => datasets.extend([Dataset::Test, Dataset::Real]),
+            "stdin" | "-" => datasets.push(Dataset::Stdin),
+            "examples" => datasets.push(Dataset::Examples),
+            _ => match name.strip_prefix("example:") {
+                Some(example) if is_file_name(example) => {
+                    datasets.push(Dataset::Example(String::from(example)))
+                }
+                None if is_file_name(name) => datasets.push(Dataset::Named(String::from(name))),
+                _ => {
+                    return Err(CliError::BadValue {
+                        flag: String::from(flag),
+                        value: String::from(value),
+                    })
+                }
+            },
+        }
+    }
+    return Ok(datasets);
+}
+
+fn parse_format(flag: &str, value: &str) -> Result<ReportFormat, CliError> {
+    return match value {
+        "text" => Ok(ReportFormat::Text),
+        "json" => Ok(ReportFormat::Json),
+        "csv" => Ok(ReportFormat::Csv),
+        _ => Err(CliError::BadValue {
+            flag: String::from(flag),
+            value: String::from(value),
+        }),
+    };
+}
+
+fn parse_days(flag: &str, value: &str) -> Result<Vec<u8>, CliError> {
+    return value
+        .split(',')
+        .map(|day| {
+            day.trim().parse::<u8>().map_err(|_| CliError::BadValue {
+                flag: String::from(flag),
+                value: String::from(value),
+            })
+        })
+        .collect();
+}
+
+fn next_value<'a>(
+    flag: &str,
+    args: &mut impl Iterator<Item = &'a String>,
+) -> Result<&'a String, CliError> {
+    return args
+        .next()
+        .ok_or_else(|| CliError::MissingValue(String::from(flag)));
+}
+
+fn parse_number<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, CliError> {
+    return value.parse::<T>().map_err(|_| CliError::BadValue {
+        flag: String::from(flag),
+        value: String::from(value),
+    });
+}
+
+struct SelectionBuilder {
+    all: bool,
+    days: Vec<u8>,
+    parts: Vec<Part>,
+    datasets: Option<Vec<Dataset>>,
+    inline: Vec<Dataset>,
+    data_dir: Option<String>,
+}
+
+impl SelectionBuilder {
+    fn new() -> Self {
+        SelectionBuilder {
+            all: false,
+            days: vec![],
+            parts: vec![Part::Part1, Part::Part2],
+            datasets: None,
+            inline: vec![],
+            data_dir: None,
+        }
+    }
+
+    // Returns false when the flag is not a selection one
+    fn accept<'a>(
+        &mut self,
+        arg: &str,
+        iter: &mut impl Iterator<Item = &'a String>,
+    ) -> Result<bool, CliError> {
+        match arg {
+            "--all" => self.all = true,
+            "--day" | "-d" => self.days.extend(parse_days(arg, next_value(arg, iter)?)?),
+            "--part" | "-p" => self.parts = parse_parts(arg, next_value(arg, iter)?)?,
+            "--dataset" | "-s" => {
+                self.datasets = Some(parse_datasets(arg, next_value(arg, iter)?)?)
+            }
+            "--inline" | "-i" => self
+                .inline
+                .push(Dataset::Inline(next_value(arg, iter)?.clone())),
+            "--data-dir" => self.data_dir = Some(next_value(arg, iter)?.clone()),
+            _ => return Ok(false),
+        }
+        return Ok(true);
+    }
+
+    fn build(self) -> Result<Selection, CliError> {
+        let days = if self.all {
+            DaySelection::All
+        } else if self.days.is_empty() {
+            return Err(CliError::MissingDaySelection);
+        } else {
+            DaySelection::Days(self.days)
+        };
+        let datasets_given = self.datasets.is_some();
+        let mut datasets = self.datasets.unwrap_or_else(|| {
+            if self.inline.is_empty() {
+                vec![Dataset::Test, Dataset::Examples, Dataset::Real]
+            } else {
+                vec![]
+            }
+        });
+        datasets.extend(self.inline);
+        return Ok(Selection {
+            days,
+            parts: self.parts,
+            datasets,
+            datasets_given,
+            data_dir: self.data_dir,
+        });
+    }
+}
+
+fn parse_run_options(args: &[String]) -> Result<RunOptions, CliError> {
+    let mut selection = SelectionBuilder::new();
+    let mut format = ReportFormat::Text;
+    let mut output = None;
+    let mut jobs = 1;
+    let mut stream = false;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if selection.accept(arg, &mut iter)? {
+            continue;
+        }
+        match arg.as_str() {
+            "--format" | "-f" => format = parse_format(arg, next_value(arg, &mut iter)?)?,
+            "--output" | "-o" => output = Some(next_value(arg, &mut iter)?.clone()),
+            "--jobs" | "-j" => jobs = parse_number(arg, next_value(arg, &mut iter)?)?,
+            "--stream" => stream = true,
+            _ => return Err(CliError::UnknownArgument(arg.clone())),
+        }
+    }
+
+    return Ok(RunOptions {
+        selection: selection.build()?,
+        format,
+        output,
+        jobs,
+        stream,
+    });
+}
+
+fn parse_bench_options(args: &[String]) -> Result<BenchOptions, CliError> {
+    let mut selection = SelectionBuilder::new();
+    let mut iterations = 20;
+    let mut warmup = 3;
+    let mut baseline = None;
+    let mut threshold = 10.0;
+    let mut save_baseline = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if selection.accept(arg, &mut iter)? {
+            continue;
+        }
+        match arg.as_str() {
+            "--iterations" | "-n" => iterations = parse_number(arg, next_value(arg, &mut iter)?)?,
+            "--warmup" | "-w" => warmup = parse_number(arg, next_value(arg, &mut iter)?)?,
+            "--baseline" | "-b" => baseline = Some(next_value(arg, &mut iter)?.clone()),
+            "--threshold" | "-t" => threshold = parse_number(arg, next_value(arg, &mut iter)?)?,
+            "--save-baseline" => save_baseline = Some(next_value(arg, &mut iter)?.clone()),
+            _ => return Err(CliError::UnknownArgument(arg.clone())),
+        }
+    }
+    if iterations == 0 {
+        return Err(CliError::BadValue {
+            flag: String::from("--iterations"),
+            value: String::from("0"),
+        });
+    }
+
+    return Ok(BenchOptions {
+        selection: selection.build()?,
+        iterations,
+        warmup,
+        baseline,
+        threshold: threshold / 100.0,
+        save_baseline,
+    });
+}
+
+pub fn parse_args(args: &[String]) -> Result<Command, CliError> {
+    let command = args.first().ok_or(CliError::MissingCommand)?;
+    return match command.as_str() {
+        "run" => Ok(Command::Run(parse_run_options(&args[1..])?)),
+        "bench" => Ok(Command::Bench(parse_bench_options(&args[1..])?)),
+        "list" => Ok(Command::List),
+        "help" | "--help" | "-h" => Ok(Command::Help),
+        _ => Err(CliError::UnknownCommand(command.clone())),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_parse_run_single_day() {
+        let command = parse_args(&to_args("run --day 15 --part 2 --dataset real")).unwrap();
+        assert_eq!(
+            command,
+            Command::Run(RunOptions {
+                selection: Selection {
+                    days: DaySelection::Days(vec![15]),
+                    parts: vec![Part::Part2],
+                    datasets: vec![Dataset::Real],
+                    datasets_given: true,
+                    data_dir: None,
+                },
+                format: ReportFormat::Text,
+                output: None,
+                jobs: 1,
+                stream: false,
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_run_all_defaults() {
+        let command = parse_args(&to_args("run --all")).unwrap();
+        assert_eq!(
+            command,
+            Command::Run(RunOptions {
+                selection: Selection {
+                    days: DaySelection::All,
+                    parts: vec![Part::Part1, Part::Part2],
+                    datasets: vec![Dataset::Test, Dataset::Examples, Dataset::Real],
+                    datasets_given: false,
+                    data_dir: None,
+                },
+                format: ReportFormat::Text,
+                output: None,
+                jobs: 1,
+                stream: false,
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_run_multiple_days() {
+        let command = parse_args(&to_args("run --day 1,2 -d 12")).unwrap();
+        if let Command::Run(options) = command {
+            assert_eq!(options.selection.days, DaySelection::Days(vec![1, 2, 12]));
+        } else {
+            panic!("Expected run command");
+        }
+    }
+
+    #[test]
+    fn test_parse_input_options() {
+        let command = parse_args(&to_args(
+            "run --day 15 -s test,big,example:small,- --data-dir /srv/aoc",
+        ))
+        .unwrap();
+        if let Command::Run(options) = command {
+            assert_eq!(
+                options.selection.datasets,
+                vec![
+                    Dataset::Test,
+                    Dataset::Named(String::from("big")),
+                    Dataset::Example(String::from("small")),
+                    Dataset::Stdin
+                ]
+            );
+            assert_eq!(options.selection.data_dir, Some(String::from("/srv/aoc")));
+        } else {
+            panic!("Expected run command");
+        }
+
+        let command = parse_args(&to_args("run --day 1 --inline 199")).unwrap();
+        if let Command::Run(options) = command {
+            assert_eq!(
+                options.selection.datasets,
+                vec![Dataset::Inline(String::from("199"))]
+            );
+        } else {
+            panic!("Expected run command");
+        }
+    }
+
+    #[test]
+    fn test_parse_report_options() {
+        let command = parse_args(&to_args(
+            "run --all --format json -o report.jsonl -j 4 --stream",
+        ))
+        .unwrap();
+        if let Command::Run(options) = command {
+            assert_eq!(options.format, ReportFormat::Json);
+            assert_eq!(options.output, Some(String::from("report.jsonl")));
+            assert_eq!(options.jobs, 4);
+            assert!(options.stream);
+        } else {
+            panic!("Expected run command");
+        }
+    }
+
+    #[test]
+    fn test_parse_bench_options() {
+        let command = parse_args(&to_args("bench --day 1 -n 50 -w 5 -b base.csv -t 20")).unwrap();
+        assert_eq!(
+            command,
+            Command::Bench(BenchOptions {
+                selection: Selection {
+                    days: DaySelection::Days(vec![1]),
+                    parts: vec![Part::Part1, Part::Part2],
+                    datasets: vec![Dataset::Test, Dataset::Examples, Dataset::Real],
+                    datasets_given: false,
+                    data_dir: None,
+                },
+                iterations: 50,
+                warmup: 5,
+                baseline: Some(String::from("base.csv")),
+                threshold: 0.2,
+                save_baseline: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(parse_args(&[]), Err(CliError::MissingCommand));
+        assert_eq!(
+            parse_args(&to_args("run")),
+            Err(CliError::MissingDaySelection)
+        );
+        assert_eq!(
+            parse_args(&to_args("run --day")),
+            Err(CliError::MissingValue(String::from("--day")))
+        );
+        assert_eq!(
+            parse_args(&to_args("run --day 1 --part 3")),
+            Err(CliError::BadValue {
+                flag: String::from("--part"),
+                value: String::from("3")
+            })
+        );
+        assert_eq!(
+            parse_args(&to_args("start")),
+            Err(CliError::UnknownCommand(String::from("start")))
+        );
+    }
+}
@@ -1,36 +1,470 @@
-use crate::utils::{Part};
+use crate::error::AocError;
+use crate::input::Input;
+use crate::solver::Solver;
+use std::cmp::{max, Ordering};
+use std::fmt;
+use std::num::IntErrorKind;
 
-pub fn parse(lines:&Vec<String>) -> Vec<u16> {
-    return lines.into_iter()
-                .filter_map(|l| l.parse::<u16>().ok())
-                .collect()
-        
+/// Reason why a line is not a depth measurement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepthError {
+    Malformed,
+    Negative,
+    TooLarge,
 }
 
-pub fn puzzle(part: &Part, lines: &Vec<String>) {
-    let values = parse(lines);
+impl fmt::Display for DepthError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DepthError::Malformed => write!(f, "not a depth"),
+            DepthError::Negative => write!(f, "negative depth"),
+            DepthError::TooLarge => write!(f, "depth larger than {}", u32::MAX),
+        }
+    }
+}
 
-    match part {
-        Part::Part1 => {
-            let count = values
-                .into_boxed_slice()
-                .windows(2)
-                .filter(|list| list[0] < list[1])
-                .count();
+/// A rejected line, numbered from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidDepth {
+    pub line: usize,
+    pub text: String,
+    pub reason: DepthError,
+}
+
+// At most this many invalid lines are listed in the error, the rest are only counted
+const LISTED_INVALID_DEPTHS: usize = 10;
+
+// Points at the first of `count` bad lines, and lists the following ones in the reason
+fn invalid_depths_error(invalid: &[InvalidDepth], count: usize) -> AocError {
+    let mut reason = format!("{} {:?}", invalid[0].reason, invalid[0].text);
+    for other in invalid.iter().take(LISTED_INVALID_DEPTHS).skip(1) {
+        reason += &format!(", line {}: {} {:?}", other.line, other.reason, other.text);
+    }
+    if count > LISTED_INVALID_DEPTHS {
+        reason += &format!(
+            " (and {} more invalid lines)",
+            count - LISTED_INVALID_DEPTHS
+        );
+    }
+    return AocError::parse(reason).at_line(invalid[0].line);
+}
+
+pub fn parse_depth(text: &str) -> Result<u32, DepthError> {
+    return text.parse::<u32>().map_err(|error| match error.kind() {
+        IntErrorKind::PosOverflow => DepthError::TooLarge,
+        _ if text.strip_prefix('-').is_some_and(|digits| {
+            !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())
+        }) =>
+        {
+            DepthError::Negative
+        }
+        _ => DepthError::Malformed,
+    });
+}
+
+/// Reads one depth measurement per line, failing with every line that is not one.
+pub fn parse(input: &str) -> Result<Vec<u32>, Vec<InvalidDepth>> {
+    let mut values = vec![];
+    let mut invalid = vec![];
+    for (index, line) in input.lines().enumerate() {
+        match parse_depth(line) {
+            Ok(value) => values.push(value),
+            Err(reason) => invalid.push(InvalidDepth {
+                line: index + 1,
+                text: String::from(line),
+                reason,
+            }),
+        }
+    }
+    if invalid.is_empty() {
+        return Ok(values);
+    }
+    return Err(invalid);
+}
+
+/// Number of values larger than the previous one.
+pub fn count_increases<T: PartialOrd>(values: &[T]) -> usize {
+    return values.windows(2).filter(|list| list[0] < list[1]).count();
+}
+
+/// Sums of each `window`-measurement sliding window, empty when there are fewer measurements.
+pub fn window_sums(values: &[u32], window: usize) -> Vec<u64> {
+    if window == 0 || values.len() < window {
+        return vec![];
+    }
+    let mut sum: u64 = values[..window].iter().map(|value| *value as u64).sum();
+    let mut sums = Vec::with_capacity(values.len() - window + 1);
+    sums.push(sum);
+    for (added, removed) in values[window..].iter().zip(values) {
+        sum = sum + *added as u64 - *removed as u64;
+        sums.push(sum);
+    }
+    return sums;
+}
+
+/// Compares the sum of each sliding window of `window` measurements with the one `step`
+/// windows later.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DepthAnalysis {
+    window: usize,
+    step: usize,
+}
 
-            println!("Result {}", count)
-        }
-        Part::Part2 => {
-            let count = values
-                .into_boxed_slice()
-                .windows(3)
-                .map(|list| list[0] + list[1] + list[2])
-                .collect::<Vec<u16>>()
-                .into_boxed_slice()
-                .windows(2)
-                .filter(|list| list[0] < list[1])
+impl DepthAnalysis {
+    /// Measurements compared one by one, as in part 1.
+    pub const SINGLE: DepthAnalysis = DepthAnalysis { window: 1, step: 1 };
+    /// Three-measurement windows compared with the next one, as in part 2.
+    pub const THREE_WINDOW: DepthAnalysis = DepthAnalysis { window: 3, step: 1 };
+
+    /// Fails when `window` or `step` is 0.
+    pub fn new(window: usize, step: usize) -> Result<DepthAnalysis, AocError> {
+        if window == 0 || step == 0 {
+            return Err(AocError::parse(format!(
+                "window and step must be positive, got {} and {}",
+                window, step
+            )));
+        }
+        return Ok(DepthAnalysis { window, step });
+    }
+
+    pub fn window(&self) -> usize {
+        return self.window;
+    }
+
+    pub fn step(&self) -> usize {
+        return self.step;
+    }
+}
+
+/// Outcome of the comparisons of a [`DepthAnalysis`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DepthReport {
+    pub increases: usize,
+    pub decreases: usize,
+    pub plateaus: usize,
+    /// Highest number of increases in a row
+    pub longest_increasing_run: usize,
+}
+
+/// Runs a [`DepthAnalysis`] one depth at a time, keeping only the last `window + step` depths.
+#[derive(Debug, Clone)]
+pub struct DepthAnalyzer {
+    analysis: DepthAnalysis,
+    last: Vec<u32>,
+    count: usize,
+    // Sums of the window ending `step` depths ago and of the window ending at the last depth
+    before: u64,
+    after: u64,
+    run: usize,
+    report: DepthReport,
+}
+
+impl DepthAnalyzer {
+    pub fn new(analysis: &DepthAnalysis) -> DepthAnalyzer {
+        return DepthAnalyzer {
+            analysis: *analysis,
+            last: vec![0; analysis.window + analysis.step],
+            count: 0,
+            before: 0,
+            after: 0,
+            run: 0,
+            report: DepthReport::default(),
+        };
+    }
+
+    pub fn push(&mut self, depth: u32) {
+        let DepthAnalysis { window, step } = self.analysis;
+        let size = self.last.len();
+        let depth_before = |back: usize| self.last[(self.count - back) % size] as u64;
+        if self.count >= step {
+            self.before += depth_before(step);
+        }
+        if self.count >= size {
+            self.before -= depth_before(size);
+        }
+        self.after += depth as u64;
+        if self.count >= window {
+            self.after -= depth_before(window);
+        }
+        if self.count + 1 >= size {
+            self.compare();
+        }
+        self.last[self.count % size] = depth;
+        self.count += 1;
+    }
+
+    fn compare(&mut self) {
+        match self.before.cmp(&self.after) {
+            Ordering::Less => {
+                self.report.increases += 1;
+                self.run += 1;
+                self.report.longest_increasing_run =
+                    max(self.report.longest_increasing_run, self.run);
+            }
+            Ordering::Equal => {
+                self.report.plateaus += 1;
+                self.run = 0;
+            }
+            Ordering::Greater => {
+                self.report.decreases += 1;
+                self.run = 0;
+            }
+        }
+    }
+
+    pub fn report(&self) -> &DepthReport {
+        return &self.report;
+    }
+}
+
+pub fn analyze<I: IntoIterator<Item = u32>>(depths: I, analysis: &DepthAnalysis) -> DepthReport {
+    let mut analyzer = DepthAnalyzer::new(analysis);
+    depths.into_iter().for_each(|depth| analyzer.push(depth));
+    return analyzer.report;
+}
+
+/// Counts the windows of `K` measurements whose sum is larger than the previous window, one
+/// depth at a time. Consecutive windows share all but their outer measurements, so comparing
+/// `a[i]` with `a[i + K]` is enough and only the last `K` depths are kept, without allocating.
+#[derive(Debug, Clone)]
+pub struct WindowIncreases<const K: usize> {
+    last: [u32; K],
+    count: usize,
+    increases: usize,
+}
+
+impl<const K: usize> WindowIncreases<K> {
+    pub fn new() -> WindowIncreases<K> {
+        const { assert!(K > 0, "window size must be positive") };
+        return WindowIncreases {
+            last: [0; K],
+            count: 0,
+            increases: 0,
+        };
+    }
+
+    pub fn push(&mut self, depth: u32) {
+        let slot = self.count % K;
+        if self.count >= K && self.last[slot] < depth {
+            self.increases += 1;
+        }
+        self.last[slot] = depth;
+        self.count += 1;
+    }
+
+    pub fn increases(&self) -> usize {
+        return self.increases;
+    }
+}
+
+impl<const K: usize> Default for WindowIncreases<K> {
+    fn default() -> Self {
+        return WindowIncreases::new();
+    }
+}
+
+/// Single pass version of `analyze(.., &DepthAnalysis::new(K, 1)?).increases`.
+pub fn count_window_increases<const K: usize, I: IntoIterator<Item = u32>>(depths: I) -> usize {
+    let mut windows = WindowIncreases::<K>::new();
+    depths.into_iter().for_each(|depth| windows.push(depth));
+    return windows.increases();
+}
+
+/// Counts of increases over the measurements and over their three-measurement windows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SonarScan {
+    pub increases: usize,
+    pub window_increases: usize,
+}
+
+/// Computes both counts in a single pass, keeping only the last three measurements.
+/// Fails like [`parse`] when some lines are not depths.
+pub fn scan(input: Input) -> Result<SonarScan, AocError> {
+    let mut single = WindowIncreases::<1>::new();
+    let mut windows = WindowIncreases::<3>::new();
+    // Only the listed invalid lines are kept, to stay in constant memory
+    let mut invalid = vec![];
+    let mut invalid_count = 0;
+    input.try_for_each_line(|line_number, line| {
+        match parse_depth(line) {
+            Ok(value) => {
+                single.push(value);
+                windows.push(value);
+            }
+            Err(reason) => {
+                if invalid_count < LISTED_INVALID_DEPTHS {
+                    invalid.push(InvalidDepth {
+                        line: line_number,
+                        text: String::from(line),
+                        reason,
+                    });
+                }
+                invalid_count += 1;
+            }
+        }
+        return Ok(());
+    })?;
+    if invalid_count > 0 {
+        return Err(invalid_depths_error(&invalid, invalid_count));
+    }
+    return Ok(SonarScan {
+        increases: single.increases(),
+        window_increases: windows.increases(),
+    });
+}
+
+/// Sonar Sweep: counts depth increases, alone then over sliding windows.
+pub struct Day1;
+
+impl Solver for Day1 {
+    type Input = Vec<u32>;
+    type Output1 = usize;
+    type Output2 = usize;
+    const STREAMING: bool = true;
+
+    fn parse(&self, input: &str) -> Result<Vec<u32>, AocError> {
+        return parse(input).map_err(|invalid| invalid_depths_error(&invalid, invalid.len()));
+    }
+
+    fn part1(&self, values: &Vec<u32>) -> Result<usize, AocError> {
+        return Ok(count_window_increases::<1, _>(values.iter().copied()));
+    }
+
+    fn part2(&self, values: &Vec<u32>) -> Result<usize, AocError> {
+        return Ok(count_window_increases::<3, _>(values.iter().copied()));
+    }
+
+    fn stream_part1(&self, input: Input) -> Option<Result<usize, AocError>> {
+        return Some(scan(input).map(|result| result.increases));
+    }
+
+    fn stream_part2(&self, input: Input) -> Option<Result<usize, AocError>> {
+        return Some(scan(input).map(|result| result.window_increases));
+    }
+
+    fn stream_parts(&self, input: Input) -> Option<Result<(usize, usize), AocError>> {
+        return Some(scan(input).map(|result| (result.increases, result.window_increases)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const EXAMPLE: &str = "199\n200\n208\n210\n200\n207\n240\n269\n260\n263\n";
+
+    #[test]
+    fn test_scan_matches_parts() {
+        let values = parse(EXAMPLE).unwrap();
+        let result = scan(Input::Stream(Box::new(Cursor::new(EXAMPLE)))).unwrap();
+        assert_eq!(result.increases, count_increases(&values));
+        assert_eq!(
+            result.window_increases,
+            count_increases(&window_sums(&values, 3))
+        );
+        assert_eq!((result.increases, result.window_increases), (7, 5));
+        let parts = Day1.stream_parts(Input::Stream(Box::new(Cursor::new(EXAMPLE))));
+        assert_eq!(parts.unwrap().unwrap(), (7, 5));
+    }
+
+    #[test]
+    fn test_analyze() {
+        let values = parse(EXAMPLE).unwrap();
+        assert_eq!(
+            analyze(values.iter().copied(), &DepthAnalysis::SINGLE),
+            DepthReport {
+                increases: 7,
+                decreases: 2,
+                plateaus: 0,
+                longest_increasing_run: 3,
+            }
+        );
+        assert_eq!(
+            analyze(values.iter().copied(), &DepthAnalysis::THREE_WINDOW),
+            DepthReport {
+                increases: 5,
+                decreases: 1,
+                plateaus: 1,
+                longest_increasing_run: 4,
+            }
+        );
+        // Pair sums 399 408 418 410 407 447 509 529 523, each compared with the one two later
+        assert_eq!(
+            analyze(values.iter().copied(), &DepthAnalysis::new(2, 2).unwrap()).increases,
+            6
+        );
+        for (window, step) in [(1, 3), (4, 2), (3, 7), (10, 1), (5, 6)] {
+            let sums = window_sums(&values, window);
+            let increases = sums
+                .iter()
+                .zip(sums.iter().skip(step))
+                .filter(|(before, after)| before < after)
                 .count();
-            println!("Result {}", count)
+            let analysis = DepthAnalysis::new(window, step).unwrap();
+            assert_eq!(
+                analyze(values.iter().copied(), &analysis).increases,
+                increases
+            );
         }
+        let large = analyze(
+            [u32::MAX, u32::MAX, 1, u32::MAX, u32::MAX],
+            &DepthAnalysis::new(2, 1).unwrap(),
+        );
+        assert!(DepthAnalysis::new(0, 1).is_err());
+        assert!(DepthAnalysis::new(3, 0).is_err());
+        assert_eq!((large.increases, large.decreases), (1, 1));
+    }
+
+    #[test]
+    fn test_count_window_increases() {
+        let values = parse(EXAMPLE).unwrap();
+        assert_eq!(
+            count_window_increases::<2, _>(values.iter().copied()),
+            analyze(values.iter().copied(), &DepthAnalysis::new(2, 1).unwrap()).increases
+        );
+        assert_eq!(
+            count_window_increases::<5, _>(values.iter().copied()),
+            analyze(values.iter().copied(), &DepthAnalysis::new(5, 1).unwrap()).increases
+        );
+        assert_eq!(count_window_increases::<20, _>(values), 0);
+    }
+
+    #[test]
+    fn test_parse_reports_invalid_lines() {
+        let input = "199\n-200\nabc\n\n4294967296\n210\n";
+        let invalid = parse(input).unwrap_err();
+        assert_eq!(
+            invalid
+                .iter()
+                .map(|invalid| (invalid.line, invalid.reason))
+                .collect::<Vec<_>>(),
+            vec![
+                (2, DepthError::Negative),
+                (3, DepthError::Malformed),
+                (4, DepthError::Malformed),
+                (5, DepthError::TooLarge),
+            ]
+        );
+        assert_eq!(
+            Day1.parse(input).unwrap_err().to_string(),
+            "Parse error at line 2: negative depth \"-200\", line 3: not a depth \"abc\", \
+             line 4: not a depth \"\", line 5: depth larger than 4294967295 \"4294967296\""
+        );
+        let many = "x\n".repeat(LISTED_INVALID_DEPTHS + 3);
+        let error = Day1.parse(&many).unwrap_err().to_string();
+        assert!(error.contains(&format!("line {}: ", LISTED_INVALID_DEPTHS)));
+        assert!(error.ends_with(" (and 3 more invalid lines)"));
+        let streamed = scan(Input::Stream(Box::new(Cursor::new(many)))).unwrap_err();
+        assert_eq!(streamed.to_string(), error);
+    }
+
+    #[test]
+    fn test_window_sums_do_not_overflow() {
+        assert_eq!(
+            window_sums(&[u32::MAX, u32::MAX, 1], 2),
+            vec![2 * u32::MAX as u64, u32::MAX as u64 + 1]
+        );
+        assert!(window_sums(&[1, 2], 3).is_empty());
     }
 }
@@ -1,8 +1,10 @@
-use crate::utils::Part;
+use crate::error::AocError;
+use crate::solver::Solver;
 use std::collections::HashMap;
 
-#[derive(PartialEq, Clone)]
-enum Cave {
+/// Cave of the map, big caves are named in upper case and may be visited any number of times.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Cave {
     Start,
     Big(String),
     Small(String),
@@ -10,7 +12,8 @@ enum Cave {
 }
 
 impl Cave {
-    fn to_str(&self) -> &str {
+    /// Name of the cave as written in the input.
+    pub fn to_str(&self) -> &str {
         return match self {
             Cave::Big(s) => s,
             Cave::Small(s) => s,
@@ -20,17 +23,20 @@ impl Cave {
     }
 }
 
-struct Link {
-    source: Cave,
-    target: Cave,
+/// Passage from one cave to another, each input line gives a link in both directions.
+pub struct Link {
+    pub source: Cave,
+    pub target: Cave,
+    /// Cost of taking the passage, 1 unless given
+    pub weight: u32,
 }
 
-type Graph = Vec<Link>;
+pub type Graph = Vec<Link>;
 
 fn is_big_cave(line: &str) -> bool {
     return line
         .chars()
-        .nth(0)
+        .next()
         .map(|c| c.is_uppercase())
         .unwrap_or(false);
 }
@@ -49,119 +55,1112 @@ fn to_cave(line: &str) -> Cave {
     };
 }
 
-fn parse_line(line: &str) -> Option<Link> {
-    let parts: Vec<&str> = line.split("-").collect();
-    let source = parts.get(0).map(|val| to_cave(val))?;
-    let target = parts.get(1).map(|val| to_cave(val))?;
-    return Some(Link {
-        source: source,
-        target: target,
+/// Parses a `<cave>-<cave>` line, optionally followed by `:<weight>`.
+pub fn parse_line(line: &str) -> Result<Link, AocError> {
+    let (caves, weight) = match line.split_once(':') {
+        Some((caves, weight)) => (
+            caves,
+            weight
+                .parse::<u32>()
+                .map_err(|_| AocError::parse(format!("bad weight {:?}", weight)))?,
+        ),
+        None => (line, 1),
+    };
+    let parts: Vec<&str> = caves.split("-").collect();
+    if parts.len() != 2 || parts.iter().any(|name| name.is_empty()) {
+        return Err(AocError::parse(format!("not a link: {:?}", caves)));
+    }
+    return Ok(Link {
+        source: to_cave(parts[0]),
+        target: to_cave(parts[1]),
+        weight,
     });
 }
 
-fn parse(lines: &Vec<String>) -> Graph {
-    return lines
+/// Builds the graph with both directions of each passage, failing on the first invalid line.
+pub fn parse(input: &str) -> Result<Graph, AocError> {
+    let links = input
+        .lines()
+        .enumerate()
+        .map(|(index, line)| parse_line(line).map_err(|error| error.at_line(index + 1)))
+        .collect::<Result<Vec<Link>, AocError>>()?;
+    return Ok(links
         .into_iter()
-        .filter_map(|line| parse_line(&line))
         .flat_map(|link| {
             let source = link.source.clone();
             let target = link.target.clone();
+            let weight = link.weight;
             vec![
                 link,
                 Link {
                     source: target,
                     target: source,
+                    weight,
                 },
             ]
         })
-        .collect();
+        .collect());
+}
+
+pub type Path<'a> = Vec<&'a Cave>;
+
+/// Index of a cave in its [`CaveSystem`].
+pub type CaveId = usize;
+
+/// Set of caves, one bit per cave id.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct CaveSet {
+    words: Vec<u64>,
 }
 
-type Path<'a> = Vec<&'a Cave>;
-type CompileGraph<'a> = HashMap<&'a str, Vec<&'a Cave>>;
+impl CaveSet {
+    pub fn with_capacity(caves: usize) -> CaveSet {
+        return CaveSet {
+            words: vec![0; caves.div_ceil(64)],
+        };
+    }
+
+    pub fn contains(&self, cave: CaveId) -> bool {
+        return self.words[cave / 64] & (1 << (cave % 64)) != 0;
+    }
+
+    pub fn insert(&mut self, cave: CaveId) {
+        self.words[cave / 64] |= 1 << (cave % 64);
+    }
+
+    pub fn remove(&mut self, cave: CaveId) {
+        self.words[cave / 64] &= !(1 << (cave % 64));
+    }
 
-fn build_map<'a>(rules: &'a Graph) -> CompileGraph {
-    let mut map: HashMap<&'a str, Vec<&'a Cave>> = HashMap::new();
-    rules.into_iter().for_each(|rule| {
-        if let Some(vals) = map.get_mut(rule.source.to_str()) {
-            vals.push(&rule.target)
-        } else {
-            map.insert(rule.source.to_str(), vec![&rule.target]);
+    /// Caves in both sets, which must have the same capacity.
+    pub fn intersection(&self, other: &CaveSet) -> CaveSet {
+        return CaveSet {
+            words: self
+                .words
+                .iter()
+                .zip(&other.words)
+                .map(|(a, b)| a & b)
+                .collect(),
+        };
+    }
+}
+
+/// Caves interned to ids, by order of appearance, with the passages of each cave stored
+/// contiguously.
+pub struct CaveSystem {
+    caves: Vec<Cave>,
+    ids: HashMap<String, CaveId>,
+    // Passages from cave `id` lead to `targets[offsets[id]..offsets[id + 1]]`
+    offsets: Vec<usize>,
+    targets: Vec<CaveId>,
+    weights: Vec<u32>,
+    start: Option<CaveId>,
+    end: Option<CaveId>,
+}
+
+impl CaveSystem {
+    /// Fails when two big caves are linked: going back and forth between them gives
+    /// infinitely many paths.
+    pub fn new(links: &Graph) -> Result<CaveSystem, AocError> {
+        let mut system = CaveSystem {
+            caves: vec![],
+            ids: HashMap::new(),
+            offsets: vec![],
+            targets: vec![],
+            weights: vec![],
+            start: None,
+            end: None,
+        };
+        let edges: Vec<(CaveId, CaveId, u32)> = links
+            .iter()
+            .map(|link| {
+                let source = system.intern(&link.source);
+                (source, system.intern(&link.target), link.weight)
+            })
+            .collect();
+
+        let mut offsets = vec![0; system.caves.len() + 1];
+        for (source, _, _) in &edges {
+            offsets[source + 1] += 1;
         }
-    });
-    return map;
+        for id in 0..system.caves.len() {
+            offsets[id + 1] += offsets[id];
+        }
+        let mut next = offsets.clone();
+        system.targets = vec![0; edges.len()];
+        system.weights = vec![0; edges.len()];
+        for (source, target, weight) in edges {
+            system.targets[next[source]] = target;
+            system.weights[next[source]] = weight;
+            next[source] += 1;
+        }
+        system.offsets = offsets;
+        if let Some((source, target)) = system.unbounded_link() {
+            return Err(AocError::parse(format!(
+                "infinite paths, big caves {} and {} are linked",
+                system.caves[source].to_str(),
+                system.caves[target].to_str()
+            )));
+        }
+        return Ok(system);
+    }
+
+    // Links are in both directions, so any cycle made only of big caves, visited any number
+    // of times, goes through a link between two of them
+    fn unbounded_link(&self) -> Option<(CaveId, CaveId)> {
+        return (0..self.len())
+            .filter(|id| matches!(self.caves[*id], Cave::Big(_)))
+            .find_map(|id| {
+                self.neighbours(id)
+                    .iter()
+                    .find(|next| matches!(self.caves[**next], Cave::Big(_)))
+                    .map(|next| (id, *next))
+            });
+    }
+
+    fn intern(&mut self, cave: &Cave) -> CaveId {
+        if let Some(id) = self.ids.get(cave.to_str()) {
+            return *id;
+        }
+        let id = self.caves.len();
+        match cave {
+            Cave::Start => self.start = Some(id),
+            Cave::End => self.end = Some(id),
+            _ => {}
+        }
+        self.caves.push(cave.clone());
+        self.ids.insert(String::from(cave.to_str()), id);
+        return id;
+    }
+
+    pub fn len(&self) -> usize {
+        return self.caves.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.caves.is_empty();
+    }
+
+    pub fn cave(&self, id: CaveId) -> &Cave {
+        return &self.caves[id];
+    }
+
+    pub fn id(&self, name: &str) -> Option<CaveId> {
+        return self.ids.get(name).copied();
+    }
+
+    pub fn neighbours(&self, id: CaveId) -> &[CaveId] {
+        return &self.targets[self.offsets[id]..self.offsets[id + 1]];
+    }
+
+    /// Weights of the passages to [`CaveSystem::neighbours`], in the same order.
+    pub fn weights(&self, id: CaveId) -> &[u32] {
+        return &self.weights[self.offsets[id]..self.offsets[id + 1]];
+    }
+
+    pub fn start(&self) -> Option<CaveId> {
+        return self.start;
+    }
+
+    pub fn end(&self) -> Option<CaveId> {
+        return self.end;
+    }
+
+    fn is_small(&self, id: CaveId) -> bool {
+        return matches!(self.caves[id], Cave::Small(_));
+    }
+
+    /// Each passage once, as linked in the input, the smaller cave id first.
+    pub fn passages(&self) -> Vec<(CaveId, CaveId)> {
+        let mut passages = vec![];
+        for id in 0..self.len() {
+            let mut loops = 0;
+            for &next in self.neighbours(id) {
+                // Both directions of a link from a cave to itself are neighbours of the cave
+                if id == next {
+                    loops += 1;
+                }
+                if id < next || (id == next && loops % 2 == 1) {
+                    passages.push((id, next));
+                }
+            }
+        }
+        return passages;
+    }
 }
 
-fn find_visitable_caves<'a>(
-    potential_node: &Vec<&'a Cave>,
-    curr_path: &Path<'a>,
-    allow_duplicate_small_cave: bool,
-) -> Vec<(bool, &'a Cave)> {
-    return potential_node
-        .iter()
-        .filter_map(|cave| match cave {
-            Cave::Small(_) => {
-                let already_exist = curr_path.contains(cave);
-                if allow_duplicate_small_cave || !already_exist {
-                    Some((allow_duplicate_small_cave && !already_exist, *cave))
-                } else {
-                    None
+/// Rules deciding how often a path may enter each cave. By default small caves are visited
+/// once and big caves any number of times.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VisitPolicy {
+    repeats: u32,
+    max_visits: u32,
+    limits: Vec<(String, u32)>,
+    forbidden: Vec<String>,
+    waypoints: Vec<String>,
+}
+
+impl Default for VisitPolicy {
+    fn default() -> Self {
+        return VisitPolicy::new();
+    }
+}
+
+impl VisitPolicy {
+    /// Most repeats accepted by [`VisitPolicy::repeats`]. The searches recurse once per cave
+    /// of the path, and every repeat can make the paths longer.
+    pub const MAX_REPEATS: u32 = 100;
+
+    pub fn new() -> VisitPolicy {
+        return VisitPolicy {
+            repeats: 0,
+            max_visits: u32::MAX,
+            limits: vec![],
+            forbidden: vec![],
+            waypoints: vec![],
+        };
+    }
+
+    /// Small caves visited once, as in part 1.
+    pub fn part1() -> VisitPolicy {
+        return VisitPolicy::new();
+    }
+
+    /// A single small cave may be visited twice, as in part 2.
+    pub fn part2() -> VisitPolicy {
+        return VisitPolicy {
+            repeats: 1,
+            ..VisitPolicy::new()
+        };
+    }
+
+    /// Number of visits to already visited small caves allowed over the whole path.
+    /// Fails above [`VisitPolicy::MAX_REPEATS`].
+    pub fn repeats(mut self, repeats: u32) -> Result<VisitPolicy, AocError> {
+        if repeats > VisitPolicy::MAX_REPEATS {
+            return Err(AocError::parse(format!(
+                "at most {} repeats are supported, got {}",
+                VisitPolicy::MAX_REPEATS,
+                repeats
+            )));
+        }
+        self.repeats = repeats;
+        return Ok(self);
+    }
+
+    /// Most visits of any small cave, repeats still count against [`VisitPolicy::repeats`].
+    pub fn max_visits(mut self, visits: u32) -> VisitPolicy {
+        self.max_visits = visits;
+        return self;
+    }
+
+    /// Most visits of the named cave, big caves included.
+    pub fn limit(mut self, cave: &str, visits: u32) -> VisitPolicy {
+        self.limits.push((String::from(cave), visits));
+        return self;
+    }
+
+    pub fn forbid(mut self, cave: &str) -> VisitPolicy {
+        self.forbidden.push(String::from(cave));
+        return self;
+    }
+
+    /// Only paths going through the named cave are kept.
+    pub fn require(mut self, cave: &str) -> VisitPolicy {
+        self.waypoints.push(String::from(cave));
+        return self;
+    }
+
+    // Paths leave from `origin` and stop at `target`, both entered only once. `start` and
+    // `end` cannot be entered unless they are one of them.
+    // `None` when a waypoint is not in the system, no path can go through it
+    fn compile(
+        &self,
+        system: &CaveSystem,
+        origin: CaveId,
+        target: CaveId,
+    ) -> Option<CompiledPolicy> {
+        let mut limits: Vec<Option<u32>> = (0..system.len())
+            .map(|id| system.is_small(id).then_some(self.max_visits))
+            .collect();
+        for (cave, visits) in &self.limits {
+            if let Some(id) = system.id(cave) {
+                limits[id] = Some(*visits);
+            }
+        }
+        for cave in &self.forbidden {
+            if let Some(id) = system.id(cave) {
+                limits[id] = Some(0);
+            }
+        }
+        for id in [system.start, system.end].into_iter().flatten() {
+            limits[id] = Some(0);
+        }
+        for id in [origin, target] {
+            limits[id] = Some(1);
+        }
+        let waypoints = self
+            .waypoints
+            .iter()
+            .map(|cave| system.id(cave))
+            .collect::<Option<Vec<CaveId>>>()?;
+        let mut tracked = CaveSet::with_capacity(system.len());
+        let mut counted = CaveSet::with_capacity(system.len());
+        for (id, limit) in limits.iter().enumerate() {
+            if limit.is_some() || waypoints.contains(&id) {
+                tracked.insert(id);
+            }
+            if limit.is_some_and(|limit| !system.is_small(id) || limit <= self.repeats) {
+                counted.insert(id);
+            }
+        }
+        return Some(CompiledPolicy {
+            limits,
+            waypoints,
+            tracked,
+            counted,
+            repeats: self.repeats,
+        });
+    }
+}
+
+// Limits by cave id, `None` for caves that may be visited any number of times.
+// Only the visits of limited caves and waypoints are tracked, and repeated visits are only
+// counted when the limit of the cave could be reached, so that the memoised states stay few
+struct CompiledPolicy {
+    limits: Vec<Option<u32>>,
+    waypoints: Vec<CaveId>,
+    tracked: CaveSet,
+    counted: CaveSet,
+    repeats: u32,
+}
+
+// How entering a cave changed the visits, to undo it when leaving
+#[derive(Debug, Clone, Copy)]
+enum Entry {
+    Untracked,
+    First,
+    Repeat,
+}
+
+// Visits of the caves limited by the policy or required by it
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Visits {
+    seen: CaveSet,
+    // Caves entered more than once with their number of visits, sorted by cave
+    repeated: Vec<(CaveId, u32)>,
+    repeats_left: u32,
+}
+
+impl Visits {
+    fn new(system: &CaveSystem, policy: &CompiledPolicy, start: CaveId) -> Visits {
+        let mut seen = CaveSet::with_capacity(system.len());
+        seen.insert(start);
+        return Visits {
+            seen,
+            repeated: vec![],
+            repeats_left: policy.repeats,
+        };
+    }
+
+    fn count(&self, cave: CaveId) -> u32 {
+        if !self.seen.contains(cave) {
+            return 0;
+        }
+        return match self.repeated.binary_search_by_key(&cave, |(id, _)| *id) {
+            Ok(index) => self.repeated[index].1,
+            Err(_) => 1,
+        };
+    }
+
+    fn can_enter(&self, system: &CaveSystem, policy: &CompiledPolicy, cave: CaveId) -> bool {
+        return match policy.limits[cave] {
+            None => true,
+            Some(limit) => {
+                let count = self.count(cave);
+                count < limit && (count == 0 || !system.is_small(cave) || self.repeats_left > 0)
+            }
+        };
+    }
+
+    fn enter(&mut self, system: &CaveSystem, policy: &CompiledPolicy, cave: CaveId) -> Entry {
+        if !policy.tracked.contains(cave) {
+            return Entry::Untracked;
+        }
+        if !self.seen.contains(cave) {
+            self.seen.insert(cave);
+            return Entry::First;
+        }
+        if system.is_small(cave) {
+            self.repeats_left -= 1;
+        }
+        if policy.counted.contains(cave) {
+            match self.repeated.binary_search_by_key(&cave, |(id, _)| *id) {
+                Ok(index) => self.repeated[index].1 += 1,
+                Err(index) => self.repeated.insert(index, (cave, 2)),
+            }
+        }
+        return Entry::Repeat;
+    }
+
+    fn leave(&mut self, system: &CaveSystem, cave: CaveId, entry: Entry) {
+        match entry {
+            Entry::Untracked => {}
+            Entry::First => self.seen.remove(cave),
+            Entry::Repeat => {
+                if system.is_small(cave) {
+                    self.repeats_left += 1;
+                }
+                if let Ok(index) = self.repeated.binary_search_by_key(&cave, |(id, _)| *id) {
+                    self.repeated[index].1 -= 1;
+                    if self.repeated[index].1 == 1 {
+                        self.repeated.remove(index);
+                    }
                 }
             }
-            Cave::Start => None,
-            _ => Some((allow_duplicate_small_cave, cave)),
-        })
-        .collect();
+        }
+    }
+
+    // Only the caves reachable from `cave` through caves that can still be entered may be
+    // visited again, the visits of the others cannot change the paths left, unless they are
+    // waypoints. Visits only get more restricted along a path, so this holds for the whole path.
+    // Returns the reachable caves with their visits
+    fn reachable_part(
+        &self,
+        system: &CaveSystem,
+        policy: &CompiledPolicy,
+        cave: CaveId,
+    ) -> (CaveSet, Visits) {
+        let mut kept = CaveSet::with_capacity(system.len());
+        kept.insert(cave);
+        let mut pending = vec![cave];
+        while let Some(current) = pending.pop() {
+            if Some(current) == system.end {
+                continue;
+            }
+            for &next in system.neighbours(current) {
+                if !kept.contains(next) && self.can_enter(system, policy, next) {
+                    kept.insert(next);
+                    pending.push(next);
+                }
+            }
+        }
+        for waypoint in &policy.waypoints {
+            kept.insert(*waypoint);
+        }
+        let visits = Visits {
+            seen: self.seen.intersection(&kept),
+            repeated: self
+                .repeated
+                .iter()
+                .filter(|(id, _)| kept.contains(*id))
+                .copied()
+                .collect(),
+            repeats_left: self.repeats_left,
+        };
+        return (kept, visits);
+    }
+
+    fn has_waypoints(&self, policy: &CompiledPolicy, target: CaveId) -> bool {
+        return policy
+            .waypoints
+            .iter()
+            .all(|cave| *cave == target || self.seen.contains(*cave));
+    }
 }
 
+// Collects the paths extending `curr_path` that reach `end` through every waypoint
 fn find_all_paths<'a>(
-    curr_cave: &Cave,
-    graph: &CompileGraph<'a>,
-    curr_path: &mut Path<'a>,
-    allow_duplicate_cave: bool,
+    system: &'a CaveSystem,
+    policy: &CompiledPolicy,
+    curr_path: &mut Vec<CaveId>,
+    visits: &mut Visits,
     result: &mut Vec<Path<'a>>,
 ) {
-    if let Cave::End = curr_cave {
-        result.push(curr_path.clone());
+    let curr_cave = curr_path[curr_path.len() - 1];
+    if Some(curr_cave) == system.end {
+        if visits.has_waypoints(policy, curr_cave) {
+            result.push(curr_path.iter().map(|id| system.cave(*id)).collect());
+        }
+        return;
+    }
+    for &cave in system.neighbours(curr_cave) {
+        if !visits.can_enter(system, policy, cave) {
+            continue;
+        }
+        curr_path.push(cave);
+        let entry = visits.enter(system, policy, cave);
+        find_all_paths(system, policy, curr_path, visits, result);
+        visits.leave(system, cave, entry);
+        curr_path.pop();
+    }
+}
+
+/// Every path from `start` to `end` following the policy.
+pub fn all_paths<'a>(system: &'a CaveSystem, policy: &VisitPolicy) -> Vec<Path<'a>> {
+    let mut result: Vec<Path> = Vec::new();
+    let compiled = system
+        .start
+        .zip(system.end)
+        .and_then(|(start, end)| Some((start, policy.compile(system, start, end)?)));
+    if let Some((start, policy)) = compiled {
+        let mut visits = Visits::new(system, &policy, start);
+        find_all_paths(system, &policy, &mut vec![start], &mut visits, &mut result);
+    }
+    return result;
+}
+
+// Counts by cave then by the visits that can still matter from it: the caves still reachable
+// with their visits once no repeat is left, all the visits before, as most caves can be entered
+type PathCountMemo = Vec<HashMap<(Option<CaveSet>, Visits), u64>>;
+
+fn count_from(
+    system: &CaveSystem,
+    policy: &CompiledPolicy,
+    cave: CaveId,
+    visits: &mut Visits,
+    memo: &mut PathCountMemo,
+) -> u64 {
+    if Some(cave) == system.end {
+        return visits.has_waypoints(policy, cave) as u64;
+    }
+    let key = if visits.repeats_left == 0 {
+        let (reachable, visits) = visits.reachable_part(system, policy, cave);
+        (Some(reachable), visits)
+    } else {
+        (None, visits.clone())
+    };
+    if let Some(count) = memo[cave].get(&key) {
+        return *count;
+    }
+    let mut count = 0;
+    for &next in system.neighbours(cave) {
+        if visits.can_enter(system, policy, next) {
+            let entry = visits.enter(system, policy, next);
+            count += count_from(system, policy, next, visits, memo);
+            visits.leave(system, next, entry);
+        }
+    }
+    memo[cave].insert(key, count);
+    return count;
+}
+
+/// Number of paths found by [`all_paths`], counted without building them: the number of
+/// ways to reach `end` only depends on the current cave and on the visits the policy limits
+/// among the caves still reachable.
+pub fn count_paths(system: &CaveSystem, policy: &VisitPolicy) -> u64 {
+    let compiled = system
+        .start
+        .zip(system.end)
+        .and_then(|(start, end)| Some((start, policy.compile(system, start, end)?)));
+    return match compiled {
+        Some((start, policy)) => {
+            let mut visits = Visits::new(system, &policy, start);
+            let mut memo = vec![HashMap::new(); system.len()];
+            count_from(system, &policy, start, &mut visits, &mut memo)
+        }
+        None => 0,
+    };
+}
+
+/// Path with the total weight of its passages.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WeightedPath<'a> {
+    pub weight: u64,
+    pub caves: Path<'a>,
+}
+
+// Lightest or heaviest path found so far, with its caves
+type Best = Option<(u64, Vec<CaveId>)>;
+
+#[allow(clippy::too_many_arguments)]
+fn find_extreme_path(
+    system: &CaveSystem,
+    policy: &CompiledPolicy,
+    target: CaveId,
+    longest: bool,
+    curr_path: &mut Vec<CaveId>,
+    weight: u64,
+    visits: &mut Visits,
+    best: &mut Best,
+) {
+    let curr_cave = curr_path[curr_path.len() - 1];
+    let improves = |best: &Best| match best {
+        Some((best_weight, _)) if longest => weight > *best_weight,
+        Some((best_weight, _)) => weight < *best_weight,
+        None => true,
+    };
+    if curr_cave == target {
+        if visits.has_waypoints(policy, target) && improves(best) {
+            *best = Some((weight, curr_path.clone()));
+        }
         return;
     }
-    if let Some(potential_caves) = graph.get(curr_cave.to_str()) {
-        let next_cave_info = find_visitable_caves(potential_caves, curr_path, allow_duplicate_cave);
-        for (new_allow_duplicate_cave, cave) in next_cave_info {
-            curr_path.push(cave);
-            find_all_paths(cave, graph, curr_path, new_allow_duplicate_cave, result);
-            curr_path.pop();
+    // Weights are never negative, a longer path cannot become the shortest one
+    if !longest && !improves(best) {
+        return;
+    }
+    let passages = system
+        .neighbours(curr_cave)
+        .iter()
+        .zip(system.weights(curr_cave));
+    for (&cave, &passage_weight) in passages {
+        if !visits.can_enter(system, policy, cave) {
+            continue;
+        }
+        curr_path.push(cave);
+        let entry = visits.enter(system, policy, cave);
+        let next_weight = weight + passage_weight as u64;
+        find_extreme_path(
+            system,
+            policy,
+            target,
+            longest,
+            curr_path,
+            next_weight,
+            visits,
+            best,
+        );
+        visits.leave(system, cave, entry);
+        curr_path.pop();
+    }
+}
+
+fn extreme_path<'a>(
+    system: &'a CaveSystem,
+    from: &str,
+    to: &str,
+    policy: &VisitPolicy,
+    longest: bool,
+) -> Option<WeightedPath<'a>> {
+    let (origin, target) = (system.id(from)?, system.id(to)?);
+    let policy = policy.compile(system, origin, target)?;
+    let mut visits = Visits::new(system, &policy, origin);
+    let mut best = None;
+    find_extreme_path(
+        system,
+        &policy,
+        target,
+        longest,
+        &mut vec![origin],
+        0,
+        &mut visits,
+        &mut best,
+    );
+    return best.map(|(weight, caves)| WeightedPath {
+        weight,
+        caves: caves.iter().map(|id| system.cave(*id)).collect(),
+    });
+}
+
+/// Lightest path between two caves following the policy, where `start` and `end` can only
+/// be the ends of the path. `None` when the caves are not connected.
+pub fn shortest_path<'a>(
+    system: &'a CaveSystem,
+    from: &str,
+    to: &str,
+    policy: &VisitPolicy,
+) -> Option<WeightedPath<'a>> {
+    return extreme_path(system, from, to, policy, false);
+}
+
+/// Heaviest path between two caves, under the rules of [`shortest_path`].
+pub fn longest_path<'a>(
+    system: &'a CaveSystem,
+    from: &str,
+    to: &str,
+    policy: &VisitPolicy,
+) -> Option<WeightedPath<'a>> {
+    return extreme_path(system, from, to, policy, true);
+}
+
+/// Graphviz attributes of some passages, keyed like [`CaveSystem::passages`].
+pub type EdgeOverlay = HashMap<(CaveId, CaveId), String>;
+
+fn dot_name(cave: &Cave) -> String {
+    return format!(
+        "\"{}\"",
+        cave.to_str().replace('\\', "\\\\").replace('"', "\\\"")
+    );
+}
+
+fn dot_style(cave: &Cave) -> &'static str {
+    return match cave {
+        Cave::Start => "shape=box, style=filled, fillcolor=palegreen",
+        Cave::End => "shape=box, style=filled, fillcolor=lightcoral",
+        Cave::Big(_) => "shape=doublecircle, style=filled, fillcolor=lightblue",
+        Cave::Small(_) => "shape=circle",
+    };
+}
+
+/// Graphviz DOT description of the cave system.
+pub fn to_dot(system: &CaveSystem) -> String {
+    return to_dot_with_overlay(system, &EdgeOverlay::new());
+}
+
+/// Graphviz DOT description of the cave system, with the attributes of `overlay` added to
+/// its passages.
+pub fn to_dot_with_overlay(system: &CaveSystem, overlay: &EdgeOverlay) -> String {
+    let mut dot = String::from("graph caves {\n");
+    for id in 0..system.len() {
+        let cave = system.cave(id);
+        dot.push_str(&format!("  {} [{}];\n", dot_name(cave), dot_style(cave)));
+    }
+    for (source, target) in system.passages() {
+        let names = format!(
+            "{} -- {}",
+            dot_name(system.cave(source)),
+            dot_name(system.cave(target))
+        );
+        match overlay.get(&(source, target)) {
+            Some(attributes) => dot.push_str(&format!("  {} [{}];\n", names, attributes)),
+            None => dot.push_str(&format!("  {};\n", names)),
+        }
+    }
+    dot.push_str("}\n");
+    return dot;
+}
+
+// Passages taken by the path, in order
+fn path_passages(system: &CaveSystem, path: &Path) -> Vec<(CaveId, CaveId)> {
+    let ids: Vec<CaveId> = path
+        .iter()
+        .filter_map(|cave| system.id(cave.to_str()))
+        .collect();
+    return ids
+        .windows(2)
+        .map(|pair| (pair[0].min(pair[1]), pair[0].max(pair[1])))
+        .collect();
+}
+
+/// Highlights the passages of `path`, labelled with the steps taking them.
+pub fn path_overlay(system: &CaveSystem, path: &Path) -> EdgeOverlay {
+    let mut steps: HashMap<(CaveId, CaveId), Vec<String>> = HashMap::new();
+    for (step, passage) in path_passages(system, path).into_iter().enumerate() {
+        steps
+            .entry(passage)
+            .or_default()
+            .push((step + 1).to_string());
+    }
+    return steps
+        .into_iter()
+        .map(|(passage, steps)| {
+            let attributes = format!("color=red, penwidth=3, label=\"{}\"", steps.join(","));
+            (passage, attributes)
+        })
+        .collect();
+}
+
+/// Draws each passage with a width growing with the number of `paths` taking it, labelled
+/// with that number.
+pub fn usage_overlay(system: &CaveSystem, paths: &[Path]) -> EdgeOverlay {
+    let mut usage: HashMap<(CaveId, CaveId), usize> = HashMap::new();
+    for path in paths {
+        for passage in path_passages(system, path) {
+            *usage.entry(passage).or_default() += 1;
         }
     }
+    let most_used = usage.values().copied().max().unwrap_or(1);
+    return usage
+        .into_iter()
+        .map(|(passage, count)| {
+            let width = 1.0 + 5.0 * count as f64 / most_used as f64;
+            let attributes = format!("color=blue, penwidth={:.1}, label=\"{}\"", width, count);
+            (passage, attributes)
+        })
+        .collect();
+}
+
+/// Cave system for benchmarks: `segments` corridors of `corridor` small caves, each ending on
+/// a big cave with two dead-end small caves, so there are `5^segments` paths for part 1
+/// whatever the number of caves.
+pub fn generate(segments: usize, corridor: usize) -> String {
+    let mut lines = vec![];
+    let mut previous = String::from("start");
+    for segment in 0..segments {
+        for index in 0..corridor {
+            let cave = format!("c{}x{}", segment, index);
+            lines.push(format!("{}-{}", previous, cave));
+            previous = cave;
+        }
+        let hub = format!("H{}", segment);
+        lines.push(format!("{}-{}", previous, hub));
+        lines.push(format!("{}-l{}a", hub, segment));
+        lines.push(format!("{}-l{}b", hub, segment));
+        previous = hub;
+    }
+    lines.push(format!("{}-end", previous));
+    return lines.join("\n") + "\n";
+}
+
+/// Passage Pathing: counts the paths through the cave system.
+pub struct Day12;
+
+impl Solver for Day12 {
+    type Input = CaveSystem;
+    type Output1 = u64;
+    type Output2 = u64;
+
+    fn parse(&self, input: &str) -> Result<CaveSystem, AocError> {
+        return CaveSystem::new(&parse(input)?);
+    }
+
+    fn part1(&self, system: &CaveSystem) -> Result<u64, AocError> {
+        return Ok(count_paths(system, &VisitPolicy::part1()));
+    }
+
+    fn part2(&self, system: &CaveSystem) -> Result<u64, AocError> {
+        return Ok(count_paths(system, &VisitPolicy::part2()));
+    }
 }
 
-pub fn puzzle(part: &Part, lines: &Vec<String>) {
-    let links = parse(lines);
-    let compiled_graph = build_map(&links);
-    match part {
-        Part::Part1 => {
-            let mut result: Vec<Path> = Vec::new();
-            find_all_paths(
-                &Cave::Start,
-                &compiled_graph,
-                &mut vec![&Cave::Start],
-                false,
-                &mut result,
-            );
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
 
-            println!("Result {}", result.len())
+    const SMALL_EXAMPLE: &str = "start-A\nstart-b\nA-c\nA-b\nb-d\nA-end\nb-end\n";
+    const MEDIUM_EXAMPLE: &str = "dc-end\nHN-start\nstart-kj\ndc-start\ndc-HN\nLN-dc\n\
+        HN-end\nkj-sa\nkj-HN\nkj-dc\n";
+
+    #[test]
+    fn test_count_matches_enumeration() {
+        for (input, expected) in [(SMALL_EXAMPLE, (10, 36)), (MEDIUM_EXAMPLE, (19, 103))] {
+            let system = Day12.parse(input).unwrap();
+            let (part1, part2) = (VisitPolicy::part1(), VisitPolicy::part2());
+            assert_eq!(count_paths(&system, &part1), expected.0);
+            assert_eq!(count_paths(&system, &part2), expected.1);
+            assert_eq!(all_paths(&system, &part1).len() as u64, expected.0);
+            assert_eq!(all_paths(&system, &part2).len() as u64, expected.1);
         }
-        Part::Part2 => {
-            let mut result: Vec<Path> = Vec::new();
-            find_all_paths(
-                &Cave::Start,
-                &compiled_graph,
-                &mut vec![&Cave::Start],
-                true,
-                &mut result,
-            );
+    }
+
+    #[test]
+    fn test_counting_beats_enumeration() {
+        let system = Day12.parse(&generate(6, 40)).unwrap();
+        let policy = VisitPolicy::part1();
+        let start = Instant::now();
+        let count = count_paths(&system, &policy);
+        let counting = start.elapsed();
+        let start = Instant::now();
+        let paths = all_paths(&system, &policy);
+        let enumeration = start.elapsed();
+        assert_eq!(count, paths.len() as u64);
+        assert!(
+            counting < enumeration,
+            "counting took {:?}, enumeration {:?}",
+            counting,
+            enumeration
+        );
+    }
+
+    #[test]
+    fn test_cave_system() {
+        let system = Day12.parse(SMALL_EXAMPLE).unwrap();
+        let a = system.id("A").unwrap();
+        let names: Vec<&str> = system
+            .neighbours(a)
+            .iter()
+            .map(|id| system.cave(*id).to_str())
+            .collect();
+        assert_eq!(names, vec!["start", "c", "b", "end"]);
+        assert_eq!(system.id("start"), system.start());
+
+        let mut visited = CaveSet::with_capacity(130);
+        visited.insert(129);
+        assert!(visited.contains(129) && !visited.contains(65));
+        visited.remove(129);
+        assert_eq!(visited, CaveSet::with_capacity(130));
+    }
+
+    fn path_names(system: &CaveSystem, policy: &VisitPolicy) -> Vec<String> {
+        let mut names: Vec<String> = all_paths(system, policy)
+            .iter()
+            .map(|path| {
+                path.iter()
+                    .map(|cave| cave.to_str())
+                    .collect::<Vec<_>>()
+                    .join(",")
+            })
+            .collect();
+        names.sort();
+        assert_eq!(names.len() as u64, count_paths(system, policy));
+        return names;
+    }
 
-            println!("Result {}", result.len())
+    #[test]
+    fn test_visit_policies() {
+        let system = Day12.parse(SMALL_EXAMPLE).unwrap();
+        assert_eq!(
+            path_names(&system, &VisitPolicy::new().forbid("A").forbid("d")),
+            vec!["start,b,end"]
+        );
+        assert_eq!(
+            path_names(&system, &VisitPolicy::new().require("c").limit("A", 2)),
+            vec!["start,A,c,A,b,end", "start,A,c,A,end", "start,b,A,c,A,end"]
+        );
+        // Each extra visit of a small cave uses a repeat, `max_visits` caps the visits of each one
+        assert_eq!(
+            path_names(&system, &VisitPolicy::new().repeats(1).unwrap()).len(),
+            36
+        );
+        assert_eq!(
+            path_names(&system, &VisitPolicy::new().repeats(2).unwrap()).len(),
+            101
+        );
+        assert_eq!(
+            path_names(
+                &system,
+                &VisitPolicy::new().repeats(2).unwrap().max_visits(2)
+            )
+            .len(),
+            54
+        );
+        assert!(path_names(&system, &VisitPolicy::new().require("unknown")).is_empty());
+    }
+
+    #[test]
+    fn test_most_repeats() {
+        // start,A,end then one more b,A loop per visit of b
+        let system = Day12.parse("start-A\nA-b\nA-end\n").unwrap();
+        let policy = VisitPolicy::new()
+            .repeats(VisitPolicy::MAX_REPEATS)
+            .unwrap();
+        assert_eq!(
+            count_paths(&system, &policy),
+            VisitPolicy::MAX_REPEATS as u64 + 2
+        );
+        assert_eq!(
+            all_paths(&system, &policy).len() as u64,
+            count_paths(&system, &policy)
+        );
+    }
+
+    #[test]
+    fn test_too_many_repeats() {
+        let error = VisitPolicy::new()
+            .repeats(VisitPolicy::MAX_REPEATS + 1)
+            .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("at most 100 repeats are supported, got 101"));
+    }
+
+    #[test]
+    fn test_linked_big_caves() {
+        let error = Day12.parse("start-A\nA-b\nb-end\nA-BC\n").err();
+        assert_eq!(
+            error.map(|error| error.to_string()),
+            Some(String::from(
+                "Parse error: infinite paths, big caves A and BC are linked"
+            ))
+        );
+        assert!(Day12.parse("start-A\nA-A\nA-end\n").is_err());
+    }
+
+    #[test]
+    fn test_dot_export() {
+        let system = Day12.parse("start-A\nA-b\nA-end\nb-end\n").unwrap();
+        assert_eq!(
+            to_dot(&system),
+            concat!(
+                "graph caves {\n",
+                "  \"start\" [shape=box, style=filled, fillcolor=palegreen];\n",
+                "  \"A\" [shape=doublecircle, style=filled, fillcolor=lightblue];\n",
+                "  \"b\" [shape=circle];\n",
+                "  \"end\" [shape=box, style=filled, fillcolor=lightcoral];\n",
+                "  \"start\" -- \"A\";\n",
+                "  \"A\" -- \"b\";\n",
+                "  \"A\" -- \"end\";\n",
+                "  \"b\" -- \"end\";\n",
+                "}\n"
+            )
+        );
+
+        let paths = all_paths(&system, &VisitPolicy::part1());
+        let longest = paths.iter().max_by_key(|path| path.len()).unwrap();
+        assert!(
+            to_dot_with_overlay(&system, &path_overlay(&system, longest))
+                .contains("  \"A\" -- \"b\" [color=red, penwidth=3, label=\"2,3\"];\n")
+        );
+        let usage = to_dot_with_overlay(&system, &usage_overlay(&system, &paths));
+        assert!(usage.contains("  \"start\" -- \"A\" [color=blue, penwidth=6.0, label=\"3\"];\n"));
+        assert!(usage.contains("  \"b\" -- \"end\" [color=blue, penwidth=2.7, label=\"1\"];\n"));
+    }
+
+    fn weighted_names(path: Option<WeightedPath>) -> Option<(u64, String)> {
+        return path.map(|path| {
+            let names: Vec<&str> = path.caves.iter().map(|cave| cave.to_str()).collect();
+            (path.weight, names.join(","))
+        });
+    }
+
+    #[test]
+    fn test_weighted_paths() {
+        let system = Day12
+            .parse("start-A:2\nstart-b:5\nA-c:1\nA-b:1\nb-d\nA-end:9\nb-end:4\n")
+            .unwrap();
+        assert_eq!(system.weights(system.start().unwrap()), &[2, 5]);
+        assert_eq!(
+            weighted_names(shortest_path(
+                &system,
+                "start",
+                "end",
+                &VisitPolicy::part1()
+            )),
+            Some((7, String::from("start,A,b,end")))
+        );
+        assert_eq!(
+            weighted_names(longest_path(&system, "start", "end", &VisitPolicy::part1())),
+            Some((17, String::from("start,b,A,c,A,end")))
+        );
+        // Ties between paths revisiting `b` or `c` once
+        assert_eq!(
+            longest_path(&system, "start", "end", &VisitPolicy::part2()).map(|path| path.weight),
+            Some(19)
+        );
+        assert_eq!(
+            weighted_names(shortest_path(&system, "c", "d", &VisitPolicy::part1())),
+            Some((3, String::from("c,A,b,d")))
+        );
+        assert_eq!(
+            shortest_path(&system, "d", "end", &VisitPolicy::new().forbid("b")),
+            None
+        );
+        // Between other caves, the light passages through `start` and `end` cannot be taken
+        let system = Day12
+            .parse("start-c:1\nstart-d:1\nc-end:1\nend-d:1\nc-x:50\nx-d:50\n")
+            .unwrap();
+        assert_eq!(
+            weighted_names(shortest_path(&system, "c", "d", &VisitPolicy::part1())),
+            Some((100, String::from("c,x,d")))
+        );
+        assert_eq!(
+            longest_path(&system, "start", "end", &VisitPolicy::part2()).map(|path| path.weight),
+            Some(102)
+        );
+        assert_eq!(
+            shortest_path(&system, "c", "d", &VisitPolicy::new().forbid("x")),
+            None
+        );
+        assert_eq!(
+            parse("start-A\nA-b:x\n")
+                .err()
+                .map(|error| error.to_string()),
+            Some(String::from("Parse error at line 2: bad weight \"x\""))
+        );
+        for line in ["", "A", "A-", "-b", "A-b-c", "A-b:"] {
+            assert!(parse_line(line).is_err(), "{:?}", line);
         }
     }
+
+    #[test]
+    fn test_generate() {
+        let links = parse(&generate(3, 4)).unwrap();
+        assert_eq!(links.len(), 2 * (3 * 7 + 1));
+        let system = CaveSystem::new(&links).unwrap();
+        assert_eq!(system.len(), 2 + 3 * 7);
+        assert_eq!(all_paths(&system, &VisitPolicy::part1()).len(), 125);
+        assert_eq!(count_paths(&system, &VisitPolicy::part1()), 125);
+    }
 }
@@ -1,12 +1,9 @@
-use crate::utils::Part;
-use std::cmp::Ordering;
-use std::collections::BinaryHeap;
-//use std::collections::HashSet;
-use hashbrown::HashSet;
-use priority_queue::PriorityQueue;
-use hashbrown::hash_map::DefaultHashBuilder;
+use crate::error::AocError;
+use crate::grid::{self, Connectivity, Grid, Point};
+use crate::solver::Solver;
 
-struct Map {
+/// Grid of risk levels, from the top left corner to the bottom right one.
+pub struct Map {
     lines: Vec<Vec<u8>>,
     max_x: u16,
     max_y: u16,
@@ -14,59 +11,39 @@ struct Map {
 
 impl Map {
     fn new(content: Vec<Vec<u8>>) -> Map {
-        let max_x = content.get(0).map(|line| line.len() - 1).unwrap_or(0) as u16;
-        let max_y = (content.len() - 1) as u16;
+        let max_x = content
+            .first()
+            .map(|line| line.len().saturating_sub(1))
+            .unwrap_or(0) as u16;
+        let max_y = content.len().saturating_sub(1) as u16;
         return Map {
             lines: content,
-            max_x: max_x,
-            max_y: max_y,
+            max_x,
+            max_y,
         };
     }
-}
-
-#[derive(PartialEq, Hash, Eq, Clone, Copy)]
-struct Pos {
-    x: u16,
-    y: u16,
-}
-
-impl Ord for Pos {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.x.cmp(&other.x).then(self.y.cmp(&other.y))
-    }
-}
-
-impl PartialOrd for Pos {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
-    }
-}
 
-#[derive(Eq, PartialEq,Hash)]
-struct WeightedPos {
-    pos: Pos,
-    previous: Option<Pos>,
-    cost_from_origin: u16,
-    estimated_total_cost: u16,
-}
-
-impl Ord for WeightedPos {
-    fn cmp(&self, other: &Self) -> Ordering {
-        other.estimated_total_cost.cmp(&self.estimated_total_cost)
+    /// Risk of entering the cell, `None` outside of the map.
+    pub fn risk(&self, point: Point) -> Option<u8> {
+        return self
+            .lines
+            .get(point.y)
+            .and_then(|line| line.get(point.x))
+            .copied();
     }
 }
 
-impl PartialOrd for WeightedPos {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
+/// Parses a line of risk digits, `None` if it is empty or any character is not a digit from 1
+/// to 9.
+pub fn parse_line(line: &str) -> Option<Vec<u8>> {
+    if line.is_empty() {
+        return None;
     }
-}
-
-fn parse_line(line: &String) -> Option<Vec<u8>> {
     let chars: Vec<&str> = line.split("").collect();
     let result = chars
         .iter()
         .filter_map(|chr| chr.parse().ok())
+        .filter(|risk| *risk > 0)
         .collect::<Vec<u8>>();
     return if result.len() == line.len() {
         Some(result)
@@ -75,108 +52,57 @@ fn parse_line(line: &String) -> Option<Vec<u8>> {
     };
 }
 
-fn to_lookup(node: &WeightedPos, map: &Map) -> Vec<WeightedPos> {
-    return [
-        if node.pos.x == 0 {
-            None
-        } else {
-            Some(Pos {
-                x: node.pos.x - 1,
-                y: node.pos.y,
-            })
-        },
-        if node.pos.x > map.max_x {
-            None
-        } else {
-            Some(Pos {
-                x: node.pos.x + 1,
-                y: node.pos.y,
-            })
-        },
-        if node.pos.y == 0 {
-            None
-        } else {
-            Some(Pos {
-                x: node.pos.x,
-                y: node.pos.y - 1,
-            })
-        },
-        if node.pos.y > map.max_y {
-            None
-        } else {
-            Some(Pos {
-                x: node.pos.x,
-                y: node.pos.y + 1,
-            })
-        },
-    ]
-    .iter()
-    .filter_map(|&it| it)
-    .filter_map(|pos| {
-        map.lines
-            .get(pos.y as usize)
-            .and_then(|line| line.get(pos.x as usize))
-            .map(|val| (pos, val))
-    })
-    .map(|tuple| {
-        let cost = node.cost_from_origin + u16::from(*tuple.1);
-        WeightedPos {
-            pos: tuple.0.clone(),
-            cost_from_origin: cost,
-            estimated_total_cost: calc_estimated_total_cost(&cost, &tuple.0, map),
-            previous: Some(node.pos),
-        }
-    })
-    .collect();
-}
-
-fn calc_estimated_total_cost(cost: &u16, pos: &Pos, map: &Map) -> u16 {
-    cost + (map.max_x - pos.x) + (map.max_y - pos.y)
+/// Lowest total risk of a path from the top left to the bottom right, `None` if unreachable.
+pub fn a_star_lookup(map: &Map) -> Option<u64> {
+    let grid = Grid::new(
+        map.max_x as usize + 1,
+        map.max_y as usize + 1,
+        Connectivity::Four,
+    );
+    let goal = Point {
+        x: map.max_x as usize,
+        y: map.max_y as usize,
+    };
+    // Parsed risk levels go from 1 to 9, so the number of steps left never overestimates
+    return grid::search(
+        &grid,
+        Point { x: 0, y: 0 },
+        goal,
+        |_, to| map.risk(to).map(u64::from),
+        grid::manhattan,
+    )
+    .map(|route| route.cost);
 }
 
-fn a_star_lookup(map: &Map) -> u16 {
-    //let mut processed: HashSet<Pos> = HashSet::new();
-    let mut processed = vec![false;(map.max_x as usize +1)*(map.max_y as usize +1)];
-    let calc_offset = |pos:&Pos| pos.x as usize + (pos.y as usize *(map.max_x as usize+1));
-    let mut priority_queue: BinaryHeap<WeightedPos> = BinaryHeap::new();
-    priority_queue.push(WeightedPos {
-        pos: Pos { x: 0, y: 0 },
-        cost_from_origin: 0,
-        previous: None,
-        estimated_total_cost: calc_estimated_total_cost(&0, &Pos { x: 0, y: 0 }, map),
-    });
-
-    while let Some(item) = priority_queue.pop() {
-        let offset = calc_offset(&item.pos);
-        if processed[offset] {
-            continue;
-        }
-        if item.pos.x == map.max_x && item.pos.y == map.max_y {
-            println!("Processed node {}", processed.len());
-            return item.cost_from_origin;
-        }
-
-        let mut new_nodes = to_lookup(&item, map);
-        while let Some(new_node) = new_nodes.pop() {
-            if processed[calc_offset(&new_node.pos)] { 
-                continue;
+/// Builds the map, failing on the first line that is not made of risk digits or whose width
+/// differs from the first line.
+pub fn parse(input: &str) -> Result<Map, AocError> {
+    let mut content: Vec<Vec<u8>> = vec![];
+    for (index, line) in input.lines().enumerate() {
+        let risks = parse_line(line).ok_or_else(|| {
+            AocError::parse(format!("not a line of risk digits 1 to 9: {:?}", line))
+                .at_line(index + 1)
+        })?;
+        if let Some(first) = content.first() {
+            if risks.len() != first.len() {
+                return Err(AocError::parse(format!(
+                    "line of {} risks, the first line has {}",
+                    risks.len(),
+                    first.len()
+                ))
+                .at_line(index + 1));
             }
-            priority_queue.push(new_node);
         }
-        processed[offset]=true;
+        content.push(risks);
     }
-    return 0;
-}
-
-fn parse(lines: &Vec<String>) -> Map {
-    let content: Vec<Vec<u8>> = lines
-        .into_iter()
-        .filter_map(|line| parse_line(&line))
-        .collect();
-    return Map::new(content);
+    if content.is_empty() {
+        return Err(AocError::parse("no line of risk digits"));
+    }
+    return Ok(Map::new(content));
 }
 
-fn extend_map(map: &Map) -> Map {
+/// Full map of part 2: the tile repeated 5 times in both directions, risks increased by tile.
+pub fn extend_map(map: &Map) -> Map {
     let mut vec: Vec<Vec<u8>> = Vec::with_capacity(map.lines.len() * 5);
     for y in 0..5 {
         for orig_line in &map.lines {
@@ -192,17 +118,55 @@ fn extend_map(map: &Map) -> Map {
     return Map::new(vec);
 }
 
-pub fn puzzle(part: &Part, lines: &Vec<String>) {
-    let map = parse(lines);
-    match part {
-        Part::Part1 => {
-            let result = a_star_lookup(&map);
-            println!("Result {}", result)
-        }
-        Part::Part2 => {
-            let new_map = extend_map(&map);
-            let result = a_star_lookup(&new_map);
-            println!("Result {}", result);
-        }
+/// Chiton: lowest total risk through the cave, then through the extended cave.
+pub struct Day15;
+
+impl Solver for Day15 {
+    type Input = Map;
+    type Output1 = u64;
+    type Output2 = u64;
+
+    fn parse(&self, input: &str) -> Result<Map, AocError> {
+        return parse(input);
+    }
+
+    fn part1(&self, map: &Map) -> Result<u64, AocError> {
+        return a_star_lookup(map).ok_or_else(|| AocError::no_solution("exit is unreachable"));
+    }
+
+    fn part2(&self, map: &Map) -> Result<u64, AocError> {
+        return a_star_lookup(&extend_map(map))
+            .ok_or_else(|| AocError::no_solution("exit is unreachable"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rejects_invalid_lines() {
+        assert_eq!(
+            parse("\n116\n138\n").err().map(|error| error.to_string()),
+            Some(String::from(
+                "Parse error at line 1: not a line of risk digits 1 to 9: \"\""
+            ))
+        );
+        assert_eq!(
+            parse("116\n1x8\n").err().map(|error| error.to_string()),
+            Some(String::from(
+                "Parse error at line 2: not a line of risk digits 1 to 9: \"1x8\""
+            ))
+        );
+        assert_eq!(
+            parse("116\n13\n").err().map(|error| error.to_string()),
+            Some(String::from(
+                "Parse error at line 2: line of 2 risks, the first line has 3"
+            ))
+        );
+        assert!(parse("116\n1381\n").is_err());
+        // A risk of 0 would make the steps left overestimate the risk left
+        assert!(parse("116\n108\n").is_err());
+        assert_eq!(a_star_lookup(&parse("116\n138\n").unwrap()), Some(12));
     }
 }
@@ -1,11 +1,14 @@
-use crate::utils::Part;
+use crate::error::{AocError, ParseFailure};
+use crate::solver::Solver;
 use std::{
     cmp::{max, min},
+    fmt,
     str::Chars,
 };
 
+/// Decoded BITS packet: a literal value or an operator over its sub-packets.
 #[derive(PartialEq, Eq, Debug)]
-enum Expr {
+pub enum Expr {
     Literal {
         version: u8,
         value: u64,
@@ -43,8 +46,9 @@ enum Expr {
     },
 }
 
+/// Reason why a transmission cannot be decoded.
 #[derive(Debug)]
-enum ParseExprError {
+pub enum ParseExprError {
     UnknownOperation,
     NotEnoughChars,
     BadHexChar,
@@ -207,12 +211,12 @@ fn parse_tuple(reader_state: &mut ReaderState) -> Result<(Expr, Expr), ParseExpr
     let mut result = parse_variable(reader_state)?;
     let second = result.pop();
     let first = result.pop();
-    if result.len() > 0 {
+    if !result.is_empty() {
         return Err(ParseExprError::BadSubExpressionCount);
     }
     return first
         .zip(second)
-        .map(|items| Ok(items))
+        .map(Ok)
         .unwrap_or(Err(ParseExprError::BadSubExpressionCount));
 }
 
@@ -237,7 +241,7 @@ fn parse_expr(reader_state: &mut ReaderState) -> Result<Expr, ParseExprError> {
             args: parse_variable(reader_state)?,
         }),
         4 => Ok(Expr::Literal {
-            version: version as u8,
+            version,
             value: parse_literal(reader_state)?,
         }),
         5 => {
@@ -268,18 +272,41 @@ fn parse_expr(reader_state: &mut ReaderState) -> Result<Expr, ParseExprError> {
     };
 }
 
-fn parse_line(line: &str) -> Result<Expr, ParseExprError> {
-    return Ok(parse_expr(&mut ReaderState::new(line))?);
+/// Decodes the outermost packet of a hexadecimal transmission.
+pub fn parse_line(line: &str) -> Result<Expr, ParseExprError> {
+    return parse_expr(&mut ReaderState::new(line));
 }
 
-fn parse(lines: &Vec<String>) -> Result<Expr, ParseExprError> {
-    return lines
-        .get(0)
-        .map(|line| parse_line(line))
-        .unwrap_or(Result::Err(ParseExprError::UnknownOperation));
+impl fmt::Display for ParseExprError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseExprError::UnknownOperation => write!(f, "unknown packet type"),
+            ParseExprError::NotEnoughChars => write!(f, "transmission ends inside a packet"),
+            ParseExprError::BadHexChar => write!(f, "not a hexadecimal digit"),
+            ParseExprError::BadSubExpressionCount => {
+                write!(f, "comparison packet without exactly two sub-packets")
+            }
+            ParseExprError::GuardReached => {
+                write!(f, "packet longer than the length given by its parent")
+            }
+            ParseExprError::BadGuardState => write!(f, "sub-packet lengths do not match"),
+        }
+    }
 }
 
-fn calc_version(expr: &Expr) -> u32 {
+impl ParseFailure for ParseExprError {}
+
+/// Decodes the transmission on the first line.
+pub fn parse(input: &str) -> Result<Expr, AocError> {
+    let line = input
+        .lines()
+        .next()
+        .ok_or_else(|| AocError::parse("no transmission line"))?;
+    return parse_line(line).map_err(|error| AocError::from(error).at_line(1));
+}
+
+/// Sum of the versions of the packet and all its sub-packets.
+pub fn calc_version(expr: &Expr) -> u32 {
     return match expr {
         Expr::Literal { version, .. } => *version as u32,
         Expr::Eq { version, a, b } | Expr::Gt { version, a, b } | Expr::Lt { version, a, b } => {
@@ -294,7 +321,8 @@ fn calc_version(expr: &Expr) -> u32 {
     };
 }
 
-fn evaluate(expr: &Expr) -> u64 {
+/// Value of the expression, comparisons evaluate to 1 when true and 0 otherwise.
+pub fn evaluate(expr: &Expr) -> u64 {
     return match expr {
         Expr::Literal { value, .. } => *value,
         Expr::Eq { a, b, .. } => {
@@ -322,25 +350,31 @@ fn evaluate(expr: &Expr) -> u64 {
         Expr::Mul { args, .. } => args.iter().fold(1, |mul, val| mul * evaluate(val)),
         Expr::Min { args, .. } => args
             .iter()
-            .fold(std::u64::MAX, |min_val, val| min(min_val, evaluate(val))),
+            .fold(u64::MAX, |min_val, val| min(min_val, evaluate(val))),
         Expr::Max { args, .. } => args
             .iter()
-            .fold(0 as u64, |max_val, val| max(max_val, evaluate(val))),
+            .fold(0_u64, |max_val, val| max(max_val, evaluate(val))),
     };
 }
 
-pub fn puzzle(part: &Part, lines: &Vec<String>) {
-    let expr_res = parse(lines);
-    let expr = expr_res.unwrap();
-    match part {
-        Part::Part1 => {
-            let result = calc_version(&expr);
-            println!("Result Version {}", result)
-        }
-        Part::Part2 => {
-            let result = evaluate(&expr);
-            println!("Result Eval {}", result);
-        }
+/// Packet Decoder: sum of the packet versions, then value of the transmission.
+pub struct Day16;
+
+impl Solver for Day16 {
+    type Input = Expr;
+    type Output1 = u32;
+    type Output2 = u64;
+
+    fn parse(&self, input: &str) -> Result<Expr, AocError> {
+        return parse(input);
+    }
+
+    fn part1(&self, expr: &Expr) -> Result<u32, AocError> {
+        return Ok(calc_version(expr));
+    }
+
+    fn part2(&self, expr: &Expr) -> Result<u64, AocError> {
+        return Ok(evaluate(expr));
     }
 }
 
@@ -349,6 +383,16 @@ mod tests {
     // Note this useful idiom: importing names from outer (for mod tests) scope.
     use super::*;
 
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse("D2FE2G\n").err().map(|error| error.to_string()),
+            Some(String::from(
+                "Parse error at line 1: not a hexadecimal digit"
+            ))
+        );
+    }
+
     #[test]
     fn test_parse_literal() {
         let parse_res = parse_line("D2FE28").unwrap();
@@ -1,31 +1,25 @@
-use crate::utils::Part;
+use crate::error::{AocError, ParseFailure};
+use crate::solver::Solver;
+use std::fmt;
 
-#[derive(Debug)]
-enum SnailFishItem {
+/// Snailfish number: a regular number or a pair of snailfish numbers.
+#[derive(Debug, Clone)]
+pub enum SnailFishItem {
     Num(u8),
     Pair(Box<SnailFishItem>, Box<SnailFishItem>),
 }
 
 impl SnailFishItem {
-    fn build_pair(left: SnailFishItem, right: SnailFishItem) -> SnailFishItem {
+    pub fn build_pair(left: SnailFishItem, right: SnailFishItem) -> SnailFishItem {
         SnailFishItem::Pair(Box::new(left), Box::new(right))
     }
-
-    fn clone(&self) -> SnailFishItem {
-        match &self {
-            Self::Num(x) => SnailFishItem::Num(*x),
-            SnailFishItem::Pair(left, right) => {
-                SnailFishItem::build_pair(left.as_ref().clone(), right.as_ref().clone())
-            }
-        }
-    }
 }
 
 impl std::fmt::Display for SnailFishItem {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        match &self {
-            &Self::Num(x) => write!(f, "{}", x),
-            &Self::Pair(l, r) => write!(f, "[{},{}]", l, r),
+        match self {
+            Self::Num(x) => write!(f, "{}", x),
+            Self::Pair(l, r) => write!(f, "[{},{}]", l, r),
         }
     }
 }
@@ -37,7 +31,7 @@ enum ParsingStackItem {
 }
 
 impl ParsingStackItem {
-    fn to_snail_fish_pair_item(self) -> Result<SnailFishItem, ParsingError> {
+    fn into_snail_fish_pair_item(self) -> Result<SnailFishItem, ParsingError> {
         match self {
             Self::Num(x) => Ok(SnailFishItem::Num(x)),
             Self::Pair(pair) => Ok(pair),
@@ -45,7 +39,7 @@ impl ParsingStackItem {
         }
     }
 
-    fn to_snail_fish_pair(self) -> Result<SnailFishItem, ParsingError> {
+    fn into_snail_fish_pair(self) -> Result<SnailFishItem, ParsingError> {
         match self {
             Self::Pair(pair) => Ok(pair),
             _ => Err(ParsingError::NotValidPair),
@@ -62,8 +56,9 @@ impl ParsingStackItem {
     }
 }
 
+/// Reason why a line is not a snailfish number.
 #[derive(Debug)]
-enum ParsingError {
+pub enum ParsingError {
     BadNumber,
     BadConvertionToPairItem,
     EmptyStackForNumber,
@@ -75,7 +70,8 @@ enum ParsingError {
     NotValidPair,
 }
 
-fn parse_line(line: &str) -> Result<SnailFishItem, ParsingError> {
+/// Parses a snailfish number written as nested `[left,right]` pairs.
+pub fn parse_line(line: &str) -> Result<SnailFishItem, ParsingError> {
     let mut stack: Vec<ParsingStackItem> = vec![];
     for car in line.chars() {
         match car {
@@ -88,11 +84,11 @@ fn parse_line(line: &str) -> Result<SnailFishItem, ParsingError> {
                 let second: SnailFishItem = stack
                     .pop()
                     .ok_or(ParsingError::EmptyStackForPairSecond)
-                    .and_then(|item| item.to_snail_fish_pair_item())?;
+                    .and_then(|item| item.into_snail_fish_pair_item())?;
                 let first: SnailFishItem = stack
                     .pop()
                     .ok_or(ParsingError::EmptyStackForPairFirst)
-                    .and_then(|item| item.to_snail_fish_pair_item())?;
+                    .and_then(|item| item.into_snail_fish_pair_item())?;
                 stack.push(ParsingStackItem::Pair(SnailFishItem::build_pair(
                     first, second,
                 )))
@@ -111,11 +107,37 @@ fn parse_line(line: &str) -> Result<SnailFishItem, ParsingError> {
     return stack
         .pop()
         .ok_or(ParsingError::EmptyStackForEndingPair)
-        .and_then(|item| item.to_snail_fish_pair());
+        .and_then(|item| item.into_snail_fish_pair());
+}
+
+impl fmt::Display for ParsingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParsingError::BadNumber => write!(f, "not a digit, bracket or comma"),
+            ParsingError::BadConvertionToPairItem => write!(f, "empty pair element"),
+            ParsingError::EmptyStackForNumber => write!(f, "number outside of a pair"),
+            ParsingError::EmptyStackForPairFirst | ParsingError::EmptyStackForPairSecond => {
+                write!(f, "pair closed without two elements")
+            }
+            ParsingError::EmptyStackForEndingPair => write!(f, "no snailfish number"),
+            ParsingError::NumberInsidePair => write!(f, "number next to a pair"),
+            ParsingError::BadBalancedPairs => write!(f, "unbalanced brackets"),
+            ParsingError::NotValidPair => write!(f, "not a pair"),
+        }
+    }
 }
 
-fn parse(lines: &Vec<String>) -> Result<Vec<SnailFishItem>, ParsingError> {
-    return lines.iter().map(|string| parse_line(&string)).collect();
+impl ParseFailure for ParsingError {}
+
+/// Parses one snailfish number per line.
+pub fn parse(input: &str) -> Result<Vec<SnailFishItem>, AocError> {
+    return input
+        .lines()
+        .enumerate()
+        .map(|(index, string)| {
+            parse_line(string).map_err(|error| AocError::from(error).at_line(index + 1))
+        })
+        .collect();
 }
 
 #[derive(PartialEq, Eq, Debug)]
@@ -265,12 +287,11 @@ fn split(item: &mut SnailFishItem) -> Option<SplitAction> {
     };
 }
 
-fn reduce(item: &mut SnailFishItem, print_intermediates: bool) {
+/// Explodes and splits the number until no rule applies anymore.
+pub fn reduce(item: &mut SnailFishItem, print_intermediates: bool) {
     loop {
-        if let None = explode(item, 0) {
-            if let None = split(item) {
-                break;
-            }
+        if explode(item, 0).is_none() && split(item).is_none() {
+            break;
         }
         if print_intermediates {
             let intermediate_result = item.to_string();
@@ -279,7 +300,8 @@ fn reduce(item: &mut SnailFishItem, print_intermediates: bool) {
     }
 }
 
-fn magnitude(item: &SnailFishItem) -> u32 {
+/// Three times the magnitude of the left element plus twice the one of the right element.
+pub fn magnitude(item: &SnailFishItem) -> u32 {
     match &item {
         SnailFishItem::Num(x) => *x as u32,
         SnailFishItem::Pair(left, right) => {
@@ -288,41 +310,51 @@ fn magnitude(item: &SnailFishItem) -> u32 {
     }
 }
 
-fn sum(left: SnailFishItem, right: SnailFishItem, print_intermediates: bool) -> SnailFishItem {
+/// Reduced pair of the two numbers.
+pub fn sum(left: SnailFishItem, right: SnailFishItem, print_intermediates: bool) -> SnailFishItem {
     let mut new_pair = SnailFishItem::build_pair(left, right);
     reduce(&mut new_pair, print_intermediates);
     return new_pair;
 }
 
-pub fn puzzle(part: &Part, lines: &Vec<String>) {
-    let snailfish_pairs = parse(lines).unwrap();
-    match part {
-        Part::Part1 => {
-            let item = snailfish_pairs
-                .into_iter()
-                .reduce(|src, dest| sum(src, dest, false))
-                .unwrap();
-
-            let mag = magnitude(&item);
-            println!("Result Version {}", mag)
-        }
-        Part::Part2 => {
-            let snailfish_pairs_ref = &snailfish_pairs;
-            let combinations: Vec<u32> = snailfish_pairs_ref
-                .iter()
-                .flat_map(|i1| {
-                    snailfish_pairs_ref.iter().map(move |i2| {
-                        if std::ptr::eq(i1, i2) {
-                            0
-                        } else {
-                            magnitude(&sum(i1.clone(), i2.clone(), false))
-                        }
-                    })
+/// Snailfish: magnitude of the sum of all numbers, then largest magnitude of two of them.
+pub struct Day18;
+
+impl Solver for Day18 {
+    type Input = Vec<SnailFishItem>;
+    type Output1 = u32;
+    type Output2 = u32;
+
+    fn parse(&self, input: &str) -> Result<Vec<SnailFishItem>, AocError> {
+        return parse(input);
+    }
+
+    fn part1(&self, snailfish_pairs: &Vec<SnailFishItem>) -> Result<u32, AocError> {
+        let item = snailfish_pairs
+            .iter()
+            .cloned()
+            .reduce(|src, dest| sum(src, dest, false))
+            .ok_or_else(|| AocError::no_solution("no snailfish number to add"))?;
+
+        return Ok(magnitude(&item));
+    }
+
+    fn part2(&self, snailfish_pairs: &Vec<SnailFishItem>) -> Result<u32, AocError> {
+        let combinations: Vec<u32> = snailfish_pairs
+            .iter()
+            .flat_map(|i1| {
+                snailfish_pairs.iter().map(move |i2| {
+                    if std::ptr::eq(i1, i2) {
+                        0
+                    } else {
+                        magnitude(&sum(i1.clone(), i2.clone(), false))
+                    }
                 })
-                .collect();
-            let max = combinations.iter().fold(0u32,|curr,val| std::cmp::max(curr,*val));
-            println!("Result Eval {}", max);
-        }
+            })
+            .collect();
+        return Ok(combinations
+            .iter()
+            .fold(0u32, |curr, val| std::cmp::max(curr, *val)));
     }
 }
 
@@ -331,6 +363,16 @@ mod tests {
     // Note this useful idiom: importing names from outer (for mod tests) scope.
     use super::*;
 
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse("[1,2]\n[[1,2],3\n")
+                .err()
+                .map(|error| error.to_string()),
+            Some(String::from("Parse error at line 2: unbalanced brackets"))
+        );
+    }
+
     #[test]
     fn test_explode_simple_left() {
         let mut value = parse_line("[[[[[9,8],1],2],3],4]").unwrap();
@@ -410,7 +452,7 @@ mod tests {
             "[[[[4,2],2],6],[8,7]]",
         ];
         let items_res: Result<Vec<SnailFishItem>, ParsingError> =
-            complex_test.iter().map(|line| parse_line(*line)).collect();
+            complex_test.iter().map(|line| parse_line(line)).collect();
         let items = items_res.unwrap();
         let mut intermediates: Vec<String> = Vec::with_capacity(items.len());
         let final_result = items
@@ -450,10 +492,4 @@ mod tests {
 
         assert_eq!(result, 4140);
     }
-
-    impl SnailFishItem {
-        fn to_string(&self) -> String {
-            format!("{}", self)
-        }
-    }
 }
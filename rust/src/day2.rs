@@ -1,91 +1,650 @@
-use crate::utils::Part;
+use crate::error::{AocError, ParseFailure};
+use crate::input::Input;
+use crate::solver::Solver;
+use std::cmp::max;
+use std::fmt;
+use std::slice;
 
-enum Instruction {
+/// Submarine command with its amount.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
     Forward(u32),
+    Back(u32),
     Down(u32),
     Up(u32),
+    /// Sets the aim, models without aim ignore it
+    Aim(u32),
+    /// Goes back to the start position
+    Reset,
 }
 
-struct Position {
-    horizontal: u32,
-    depth: u32,
-}
-
-struct PositionWithAim {
-    horizontal: u32,
-    depth: u32,
-    aim: u32,
-}
-
-fn parse_line(line: &String) -> Option<Instruction> {
-    let parts: Vec<&str> = line.split(" ").collect();
-    let value: u32 = parts
-        .get(1)
-        .and_then(|val_str| val_str.parse::<u32>().ok())?;
-    return parts.get(0).map(|dir_str| match dir_str.as_ref() {
-        "forward" => Instruction::Forward(value),
-        "up" => Instruction::Up(value),
-        _ => Instruction::Down(value),
-    });
-}
-
-fn parse(lines: &Vec<String>) -> Vec<Instruction> {
-    return lines.into_iter().filter_map(|l| parse_line(&l)).collect();
-}
-
-pub fn puzzle(part: &Part, lines: &Vec<String>) {
-    let instructions = parse(lines);
-
-    match part {
-        Part::Part1 => {
-            let result: Position = instructions.iter().fold(
-                Position {
-                    horizontal: 0,
-                    depth: 0,
-                },
-                |result, intruction| match intruction {
-                    Instruction::Up(value) => Position {
-                        depth: result.depth - value,
-                        ..result
-                    },
-                    Instruction::Down(value) => Position {
-                        depth: result.depth + value,
-                        ..result
-                    },
-                    Instruction::Forward(value) => Position {
-                        horizontal: result.horizontal + value,
-                        ..result
-                    },
-                },
-            );
-
-            println!("Result {}", result.horizontal * result.depth)
+/// Reason why a line is not a command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandError {
+    UnknownVerb(String),
+    MissingAmount(String),
+    BadAmount(String),
+    UnexpectedArgument(String),
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CommandError::UnknownVerb(verb) => write!(f, "unknown command {:?}", verb),
+            CommandError::MissingAmount(verb) => write!(f, "missing amount after {:?}", verb),
+            CommandError::BadAmount(amount) => write!(f, "invalid amount {:?}", amount),
+            CommandError::UnexpectedArgument(argument) => {
+                write!(f, "unexpected argument {:?}", argument)
+            }
+        }
+    }
+}
+
+impl ParseFailure for CommandError {}
+
+/// Parses a `<verb> [<amount>]` line, `None` for blank lines and `#` comments.
+pub fn parse_line(line: &str) -> Result<Option<Instruction>, CommandError> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+    let mut words = line.split_whitespace();
+    let verb = words.next().unwrap_or_default();
+    let amount = words.next();
+    if let Some(argument) = words.next() {
+        return Err(CommandError::UnexpectedArgument(String::from(argument)));
+    }
+    if verb == "reset" {
+        return match amount {
+            Some(argument) => Err(CommandError::UnexpectedArgument(String::from(argument))),
+            None => Ok(Some(Instruction::Reset)),
+        };
+    }
+    let command: fn(u32) -> Instruction = match verb {
+        "forward" => Instruction::Forward,
+        "back" => Instruction::Back,
+        "down" => Instruction::Down,
+        "up" => Instruction::Up,
+        "aim" => Instruction::Aim,
+        _ => return Err(CommandError::UnknownVerb(String::from(verb))),
+    };
+    let amount = amount.ok_or_else(|| CommandError::MissingAmount(String::from(verb)))?;
+    let value = amount
+        .parse::<u32>()
+        .map_err(|_| CommandError::BadAmount(String::from(amount)))?;
+    return Ok(Some(command(value)));
+}
+
+/// Parses every command, failing on the first line that is not one.
+pub fn parse(input: &str) -> Result<Vec<Instruction>, AocError> {
+    return parse_course(input).map(|course| course.instructions);
+}
+
+/// Instructions along with the 1-based line each one comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Course {
+    pub instructions: Vec<Instruction>,
+    pub lines: Vec<usize>,
+}
+
+impl Course {
+    /// Same as [`follow`], reporting a failed instruction at its line.
+    pub fn follow<M: Movement>(&self, surfacing: Surfacing) -> Result<M, AocError> {
+        return follow(&self.instructions, surfacing)
+            .map_err(|error| move_error(error.fault, self.lines[error.index]));
+    }
+}
+
+pub fn parse_course(input: &str) -> Result<Course, AocError> {
+    let mut course = Course {
+        instructions: vec![],
+        lines: vec![],
+    };
+    for (index, line) in input.lines().enumerate() {
+        if let Some(instruction) =
+            parse_line(line).map_err(|error| AocError::from(error).at_line(index + 1))?
+        {
+            course.instructions.push(instruction);
+            course.lines.push(index + 1);
+        }
+    }
+    return Ok(course);
+}
+
+/// What to do when an instruction would take the submarine above the surface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Surfacing {
+    /// Stop at depth 0
+    Clamp,
+    Error,
+}
+
+/// Reason why an instruction cannot be applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveFault {
+    AboveSurface,
+    Overflow,
+}
+
+impl fmt::Display for MoveFault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MoveFault::AboveSurface => write!(f, "takes the submarine above the surface"),
+            MoveFault::Overflow => write!(f, "overflows the position"),
         }
-        Part::Part2 => {
-            let result: PositionWithAim = instructions.iter().fold(
+    }
+}
+
+/// Fault of the instruction at `index`, counted from 0 among the instructions. Comments and
+/// blank lines are not instructions, see [`Course`] for the source lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MoveError {
+    pub index: usize,
+    pub fault: MoveFault,
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "instruction #{} {}", self.index + 1, self.fault)
+    }
+}
+
+fn move_error(fault: MoveFault, line_number: usize) -> AocError {
+    return AocError::no_solution(format!("instruction {}", fault)).at_line(line_number);
+}
+
+fn checked(value: Option<i64>) -> Result<i64, MoveFault> {
+    return value.ok_or(MoveFault::Overflow);
+}
+
+fn below_surface(depth: i64, surfacing: Surfacing) -> Result<i64, MoveFault> {
+    if depth >= 0 {
+        return Ok(depth);
+    }
+    return match surfacing {
+        Surfacing::Clamp => Ok(0),
+        Surfacing::Error => Err(MoveFault::AboveSurface),
+    };
+}
+
+/// How the submarine moves on each instruction, so one instruction stream can drive
+/// several interpretations.
+pub trait Movement: Copy {
+    const START: Self;
+
+    fn apply(self, instruction: &Instruction, surfacing: Surfacing) -> Result<Self, MoveFault>;
+
+    fn horizontal(&self) -> i64;
+
+    fn depth(&self) -> i64;
+
+    /// 0 for models without aim.
+    fn aim(&self) -> i64 {
+        return 0;
+    }
+
+    /// Horizontal position times depth, the puzzle answer.
+    fn product(&self) -> Result<i64, MoveFault> {
+        return checked(self.horizontal().checked_mul(self.depth()));
+    }
+}
+
+/// Position when `up` and `down` change the depth directly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub horizontal: i64,
+    pub depth: i64,
+}
+
+/// Position when `up` and `down` only change the aim, the depth moves on `forward`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PositionWithAim {
+    pub horizontal: i64,
+    pub depth: i64,
+    pub aim: i64,
+}
+
+impl Movement for Position {
+    const START: Position = Position {
+        horizontal: 0,
+        depth: 0,
+    };
+
+    fn apply(self, instruction: &Instruction, surfacing: Surfacing) -> Result<Position, MoveFault> {
+        return Ok(match *instruction {
+            Instruction::Up(value) => Position {
+                depth: below_surface(checked(self.depth.checked_sub(value as i64))?, surfacing)?,
+                ..self
+            },
+            Instruction::Down(value) => Position {
+                depth: checked(self.depth.checked_add(value as i64))?,
+                ..self
+            },
+            Instruction::Forward(value) => Position {
+                horizontal: checked(self.horizontal.checked_add(value as i64))?,
+                ..self
+            },
+            Instruction::Back(value) => Position {
+                horizontal: checked(self.horizontal.checked_sub(value as i64))?,
+                ..self
+            },
+            Instruction::Aim(_) => self,
+            Instruction::Reset => Position::START,
+        });
+    }
+
+    fn horizontal(&self) -> i64 {
+        return self.horizontal;
+    }
+
+    fn depth(&self) -> i64 {
+        return self.depth;
+    }
+}
+
+impl Movement for PositionWithAim {
+    const START: PositionWithAim = PositionWithAim {
+        horizontal: 0,
+        depth: 0,
+        aim: 0,
+    };
+
+    fn apply(
+        self,
+        instruction: &Instruction,
+        surfacing: Surfacing,
+    ) -> Result<PositionWithAim, MoveFault> {
+        return Ok(match *instruction {
+            Instruction::Up(value) => PositionWithAim {
+                aim: checked(self.aim.checked_sub(value as i64))?,
+                ..self
+            },
+            Instruction::Down(value) => PositionWithAim {
+                aim: checked(self.aim.checked_add(value as i64))?,
+                ..self
+            },
+            Instruction::Forward(value) => {
+                let dive = checked(self.aim.checked_mul(value as i64))?;
                 PositionWithAim {
-                    horizontal: 0,
-                    depth: 0,
-                    aim: 0,
-                },
-                |result, intruction| match intruction {
-                    Instruction::Up(value) => PositionWithAim {
-                        aim: result.aim - value,
-                        ..result
-                    },
-                    Instruction::Down(value) => PositionWithAim {
-                        aim: result.aim + value,
-                        ..result
-                    },
-                    Instruction::Forward(value) => PositionWithAim {
-                        horizontal: result.horizontal + value,
-                        depth: result.depth + result.aim * value,
-                        ..result
-                    },
-                },
-            );
-
-            println!("Result {}", result.horizontal * result.depth)
+                    horizontal: checked(self.horizontal.checked_add(value as i64))?,
+                    depth: below_surface(checked(self.depth.checked_add(dive))?, surfacing)?,
+                    ..self
+                }
+            }
+            // Backing up retraces the dive, the depth changes the other way
+            Instruction::Back(value) => {
+                let dive = checked(self.aim.checked_mul(value as i64))?;
+                PositionWithAim {
+                    horizontal: checked(self.horizontal.checked_sub(value as i64))?,
+                    depth: below_surface(checked(self.depth.checked_sub(dive))?, surfacing)?,
+                    ..self
+                }
+            }
+            Instruction::Aim(value) => PositionWithAim {
+                aim: value as i64,
+                ..self
+            },
+            Instruction::Reset => PositionWithAim::START,
+        });
+    }
+
+    fn horizontal(&self) -> i64 {
+        return self.horizontal;
+    }
+
+    fn depth(&self) -> i64 {
+        return self.depth;
+    }
+
+    fn aim(&self) -> i64 {
+        return self.aim;
+    }
+}
+
+/// Final position of the model after applying the instructions from the surface.
+pub fn follow<M: Movement>(
+    instructions: &[Instruction],
+    surfacing: Surfacing,
+) -> Result<M, MoveError> {
+    let mut position = M::START;
+    for (index, instruction) in instructions.iter().enumerate() {
+        position = position
+            .apply(instruction, surfacing)
+            .map_err(|fault| MoveError { index, fault })?;
+    }
+    return Ok(position);
+}
+
+/// Final positions of both models in a single pass, without keeping the instructions.
+pub fn scan(input: Input, surfacing: Surfacing) -> Result<(Position, PositionWithAim), AocError> {
+    let mut position = Position::START;
+    let mut position_with_aim = PositionWithAim::START;
+    input.try_for_each_line(|line_number, line| {
+        if let Some(instruction) =
+            parse_line(line).map_err(|error| AocError::from(error).at_line(line_number))?
+        {
+            let error = |fault| move_error(fault, line_number);
+            position = position.apply(&instruction, surfacing).map_err(error)?;
+            position_with_aim = position_with_aim
+                .apply(&instruction, surfacing)
+                .map_err(error)?;
+        }
+        return Ok(());
+    })?;
+    return Ok((position, position_with_aim));
+}
+
+/// State of the submarine after `step` instructions, step 0 being the start.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrajectoryPoint {
+    pub step: usize,
+    pub horizontal: i64,
+    pub depth: i64,
+    pub aim: i64,
+}
+
+impl TrajectoryPoint {
+    fn of<M: Movement>(step: usize, position: &M) -> TrajectoryPoint {
+        return TrajectoryPoint {
+            step,
+            horizontal: position.horizontal(),
+            depth: position.depth(),
+            aim: position.aim(),
+        };
+    }
+}
+
+/// Iterator over the successive states of a model, see [`trajectory`].
+pub struct Trajectory<'a, M> {
+    instructions: slice::Iter<'a, Instruction>,
+    surfacing: Surfacing,
+    position: Option<M>,
+    step: usize,
+}
+
+impl<M: Movement> Iterator for Trajectory<'_, M> {
+    type Item = Result<TrajectoryPoint, MoveError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let position = self.position?;
+        if self.step == 0 {
+            self.step = 1;
+            return Some(Ok(TrajectoryPoint::of(0, &position)));
         }
+        let instruction = self.instructions.next()?;
+        let index = self.step - 1;
+        return match position.apply(instruction, self.surfacing) {
+            Ok(next) => {
+                self.position = Some(next);
+                self.step += 1;
+                Some(Ok(TrajectoryPoint::of(index + 1, &next)))
+            }
+            Err(fault) => {
+                // Nothing sensible follows a failed instruction
+                self.position = None;
+                Some(Err(MoveError { index, fault }))
+            }
+        };
+    }
+}
+
+/// Every state of the model from the start, one per instruction, ending on the first error.
+pub fn trajectory<M: Movement>(
+    instructions: &[Instruction],
+    surfacing: Surfacing,
+) -> Trajectory<'_, M> {
+    return Trajectory {
+        instructions: instructions.iter(),
+        surfacing,
+        position: Some(M::START),
+        step: 0,
+    };
+}
+
+/// One `step,horizontal,depth,aim` row per point, after a header.
+pub fn trajectory_to_csv(points: &[TrajectoryPoint]) -> String {
+    let mut csv = String::from("step,horizontal,depth,aim\n");
+    for point in points {
+        csv.push_str(&format!(
+            "{},{},{},{}\n",
+            point.step, point.horizontal, point.depth, point.aim
+        ));
+    }
+    return csv;
+}
+
+/// Course as a polyline, horizontal position to the right and depth downwards.
+/// Both axes are stretched to fill `width` x `height`, depths are often much larger.
+pub fn trajectory_to_svg(points: &[TrajectoryPoint], width: u32, height: u32) -> String {
+    let min_x = points
+        .iter()
+        .map(|point| point.horizontal)
+        .min()
+        .unwrap_or(0);
+    let max_x = points
+        .iter()
+        .map(|point| point.horizontal)
+        .max()
+        .unwrap_or(0);
+    let min_y = points.iter().map(|point| point.depth).min().unwrap_or(0);
+    let max_y = points.iter().map(|point| point.depth).max().unwrap_or(0);
+    let coordinates: Vec<String> = points
+        .iter()
+        .map(|point| format!("{},{}", point.horizontal, point.depth))
+        .collect();
+    return format!(
+        concat!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" ",
+            "viewBox=\"{} {} {} {}\" preserveAspectRatio=\"none\">\n",
+            "  <polyline fill=\"none\" stroke=\"black\" stroke-width=\"1\" ",
+            "vector-effect=\"non-scaling-stroke\" points=\"{}\"/>\n",
+            "</svg>\n"
+        ),
+        width,
+        height,
+        min_x,
+        min_y,
+        max(max_x - min_x, 1),
+        max(max_y - min_y, 1),
+        coordinates.join(" ")
+    );
+}
+
+fn product<M: Movement>(position: M) -> Result<i64, AocError> {
+    return position
+        .product()
+        .map_err(|fault| AocError::no_solution(format!("{:?} in the final product", fault)));
+}
+
+/// Dive!: product of the final horizontal position and depth, without then with aim.
+pub struct Day2;
+
+// The puzzle never goes above the surface, doing so means the input is wrong
+impl Solver for Day2 {
+    type Input = Course;
+    type Output1 = i64;
+    type Output2 = i64;
+    const STREAMING: bool = true;
+
+    fn parse(&self, input: &str) -> Result<Course, AocError> {
+        return parse_course(input);
+    }
+
+    fn part1(&self, course: &Course) -> Result<i64, AocError> {
+        return product(course.follow::<Position>(Surfacing::Error)?);
+    }
+
+    fn part2(&self, course: &Course) -> Result<i64, AocError> {
+        return product(course.follow::<PositionWithAim>(Surfacing::Error)?);
+    }
+
+    fn stream_part1(&self, input: Input) -> Option<Result<i64, AocError>> {
+        return Some(scan(input, Surfacing::Error).and_then(|(result, _)| product(result)));
+    }
+
+    fn stream_part2(&self, input: Input) -> Option<Result<i64, AocError>> {
+        return Some(scan(input, Surfacing::Error).and_then(|(_, result)| product(result)));
+    }
+
+    fn stream_parts(&self, input: Input) -> Option<Result<(i64, i64), AocError>> {
+        return Some(
+            scan(input, Surfacing::Error)
+                .and_then(|(position, with_aim)| Ok((product(position)?, product(with_aim)?))),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_matches_parts() {
+        let example = "forward 5\ndown 5\nforward 8\nup 3\ndown 8\nforward 2\n";
+        let instructions = parse(example).unwrap();
+        let (position, position_with_aim) = scan(Input::Text(example), Surfacing::Error).unwrap();
+        assert_eq!(Ok(position), follow(&instructions, Surfacing::Error));
+        assert_eq!(
+            Ok(position_with_aim),
+            follow(&instructions, Surfacing::Error)
+        );
+        assert_eq!(position.product(), Ok(150));
+        assert_eq!(position_with_aim.product(), Ok(900));
+    }
+
+    #[test]
+    fn test_extended_commands() {
+        let instructions =
+            parse("# warm up\nforward 3\nreset\n\naim 2\nforward 5\nback 1\n").unwrap();
+        assert_eq!(instructions.len(), 5);
+        assert_eq!(
+            follow::<Position>(&instructions, Surfacing::Error),
+            Ok(Position {
+                horizontal: 4,
+                depth: 0,
+            })
+        );
+        assert_eq!(
+            follow::<PositionWithAim>(&instructions, Surfacing::Error),
+            Ok(PositionWithAim {
+                horizontal: 4,
+                depth: 8,
+                aim: 2,
+            })
+        );
+    }
+
+    #[test]
+    fn test_command_errors() {
+        assert_eq!(
+            parse_line("sideways 3"),
+            Err(CommandError::UnknownVerb(String::from("sideways")))
+        );
+        assert_eq!(
+            parse_line("up"),
+            Err(CommandError::MissingAmount(String::from("up")))
+        );
+        assert_eq!(
+            parse_line("down -1"),
+            Err(CommandError::BadAmount(String::from("-1")))
+        );
+        assert_eq!(
+            parse_line("reset 2"),
+            Err(CommandError::UnexpectedArgument(String::from("2")))
+        );
+        assert_eq!(
+            parse("forward 1\nfly 2\n").unwrap_err().to_string(),
+            "Parse error at line 2: unknown command \"fly\""
+        );
+    }
+
+    #[test]
+    fn test_surfacing() {
+        let instructions = parse("down 2\nforward 1\nup 5\nforward 1\n").unwrap();
+        assert_eq!(
+            follow::<Position>(&instructions, Surfacing::Error),
+            Err(MoveError {
+                index: 2,
+                fault: MoveFault::AboveSurface,
+            })
+        );
+        assert_eq!(
+            follow::<Position>(&instructions, Surfacing::Clamp),
+            Ok(Position {
+                horizontal: 2,
+                depth: 0,
+            })
+        );
+        // Aim 2 then -3: the first forward dives to 2, the second one rises by 3
+        assert_eq!(
+            follow::<PositionWithAim>(&instructions, Surfacing::Error),
+            Err(MoveError {
+                index: 3,
+                fault: MoveFault::AboveSurface,
+            })
+        );
+        assert_eq!(
+            follow::<PositionWithAim>(&instructions, Surfacing::Clamp).map(|position| position.aim),
+            Ok(-3)
+        );
+
+        // Errors point at the source line, comments and blank lines included
+        let input = "# dive\ndown 2\n\nforward 1\nup 5\n";
+        let expected =
+            "No solution found at line 5: instruction takes the submarine above the surface";
+        let course = Day2.parse(input).unwrap();
+        assert_eq!(course.lines, vec![2, 4, 5]);
+        assert_eq!(Day2.part1(&course).unwrap_err().to_string(), expected);
+        let streamed = Day2.stream_part1(Input::Text(input)).unwrap();
+        assert_eq!(streamed.unwrap_err().to_string(), expected);
+    }
+
+    #[test]
+    fn test_overflow() {
+        let instructions = vec![Instruction::Aim(u32::MAX), Instruction::Forward(u32::MAX)];
+        assert_eq!(
+            follow::<PositionWithAim>(&instructions, Surfacing::Error),
+            Err(MoveError {
+                index: 1,
+                fault: MoveFault::Overflow,
+            })
+        );
+    }
+
+    #[test]
+    fn test_trajectory() {
+        let instructions = parse("forward 5\ndown 5\nforward 8\nup 3\n").unwrap();
+        let points: Vec<TrajectoryPoint> =
+            trajectory::<PositionWithAim>(&instructions, Surfacing::Error)
+                .collect::<Result<_, _>>()
+                .unwrap();
+        assert_eq!(points.len(), 5);
+        assert_eq!(
+            points[3],
+            TrajectoryPoint {
+                step: 3,
+                horizontal: 13,
+                depth: 40,
+                aim: 5,
+            }
+        );
+        assert_eq!(
+            trajectory_to_csv(&points[..2]),
+            "step,horizontal,depth,aim\n0,0,0,0\n1,5,0,0\n"
+        );
+        assert!(trajectory_to_svg(&points, 400, 200)
+            .contains("viewBox=\"0 0 13 40\" preserveAspectRatio=\"none\">\n  <polyline"));
+        assert!(trajectory_to_svg(&points, 400, 200).contains("points=\"0,0 5,0 5,0 13,40 13,40\""));
+
+        let mut failing = trajectory::<Position>(&instructions[3..], Surfacing::Error);
+        assert!(failing.next().unwrap().is_ok());
+        assert_eq!(
+            failing.next(),
+            Some(Err(MoveError {
+                index: 0,
+                fault: MoveFault::AboveSurface,
+            }))
+        );
+        assert_eq!(failing.next(), None);
     }
 }
@@ -1,11 +1,13 @@
-use crate::utils::Part;
+use crate::error::{AocError, ParseFailure};
+use crate::solver::Solver;
 use regex::{Captures, Regex};
-use std::{collections::BTreeSet, mem::size_of, ops::RangeInclusive, time::Instant};
+use std::{collections::BTreeSet, fmt, mem::size_of, ops::RangeInclusive, time::Instant};
 
-struct Cuboid {
-    x: RangeInclusive<i32>,
-    y: RangeInclusive<i32>,
-    z: RangeInclusive<i32>,
+/// Box of cubes, bounds included on the three axes.
+pub struct Cuboid {
+    pub x: RangeInclusive<i32>,
+    pub y: RangeInclusive<i32>,
+    pub z: RangeInclusive<i32>,
 }
 
 type RealSizeTuple = (i32, u32, i32);
@@ -40,17 +42,14 @@ impl AdaptativeGridAxis {
                 .as_slice()
                 .windows(2)
                 .map(|v| (v[0], v[1]))
-                .enumerate()
-                .map(|(_, (r1, r2))| (r1, (r2 as i64 - r1 as i64) as u32, r2 - 1))
+                .map(|(r1, r2)| (r1, (r2 as i64 - r1 as i64) as u32, r2 - 1))
                 .collect(),
             range: (-max, max),
         }
     }
 
     fn real_range_to_short(&self, range: &RangeInclusive<i32>) -> Option<RangeInclusive<u32>> {
-        if *range.end() < self.range.0 {
-            return None;
-        } else if *range.start() > self.range.1 {
+        if *range.end() < self.range.0 || *range.start() > self.range.1 {
             return None;
         }
         let effective_start = std::cmp::max(*range.start(), self.range.0);
@@ -65,10 +64,7 @@ impl AdaptativeGridAxis {
             .collect::<Vec<u32>>();
         return match found.first() {
             None => None,
-            Some(first) => match found.last() {
-                None => None,
-                Some(last) => Some(RangeInclusive::new(*first, *last)),
-            },
+            Some(first) => found.last().map(|last| RangeInclusive::new(*first, *last)),
         };
     }
 
@@ -115,13 +111,13 @@ const PACKED_TYPE_SIZE: u8 = 32;
 const PACKED_TYPE_ALL_SET: u32 = u32::MAX;
 
 impl AdaptativeGrid {
-    fn new<'a>(cuboids: &Vec<&Cuboid>, max: i32) -> AdaptativeGrid {
+    fn new(cuboids: &Vec<&Cuboid>, max: i32) -> AdaptativeGrid {
         let x_coords = AdaptativeGridAxis::new(cuboids, max, |cuboid| &cuboid.x);
         let y_coords = AdaptativeGridAxis::new(cuboids, max, |cuboid| &cuboid.y);
         let z_coords = AdaptativeGridAxis::new(cuboids, max, |cuboid| &cuboid.z);
         let total_size = x_coords.items.len() * y_coords.items.len() * z_coords.items.len();
         let compact_size = total_size / (PACKED_TYPE_SIZE as usize)
-            + if total_size % (PACKED_TYPE_SIZE as usize) > 0 {
+            + if !total_size.is_multiple_of(PACKED_TYPE_SIZE as usize) {
                 1
             } else {
                 0
@@ -208,9 +204,10 @@ fn calc_mask(pos: usize, start: (usize, u8), end: (usize, u8)) -> u32 {
     mask
 }
 
-struct Instruction {
-    on: bool,
-    cuboid: Cuboid,
+/// Reboot step turning every cube of the cuboid on or off.
+pub struct Instruction {
+    pub on: bool,
+    pub cuboid: Cuboid,
 }
 
 #[derive(Debug)]
@@ -250,13 +247,27 @@ fn parse_line(line: &str, parser: &Regex) -> Result<Instruction, ParsingError> {
     });
 }
 
-fn parse(lines: &Vec<String>) -> Result<Vec<Instruction>, ParsingError> {
+impl fmt::Display for ParsingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParsingError::BadFormat => write!(f, "not an `on|off x=a..b,y=c..d,z=e..f` step"),
+        }
+    }
+}
+
+impl ParseFailure for ParsingError {}
+
+/// Parses one `on|off x=a..b,y=c..d,z=e..f` step per line.
+pub fn parse(input: &str) -> Result<Vec<Instruction>, AocError> {
     let parser: Regex =
         Regex::new(r"(on|off)\s+x=(-?\d+)\.\.(-?\d+),y=(-?\d+)\.\.(-?\d+),z=(-?\d+)\.\.(-?\d+)")
             .or(Err(ParsingError::BadFormat))?;
-    return lines
-        .iter()
-        .map(|string| parse_line(&string, &parser))
+    return input
+        .lines()
+        .enumerate()
+        .map(|(index, string)| {
+            parse_line(string, &parser).map_err(|error| AocError::from(error).at_line(index + 1))
+        })
         .collect();
 }
 
@@ -274,7 +285,8 @@ fn intersect_segments(
     }
 }
 
-fn intersect_cuboid(source_cuboid: &Cuboid, target: &Cuboid) -> Option<Cuboid> {
+/// Common part of two cuboids, `None` when they do not overlap.
+pub fn intersect_cuboid(source_cuboid: &Cuboid, target: &Cuboid) -> Option<Cuboid> {
     let intersect_x = intersect_segments(&source_cuboid.x, &target.x)?;
     let intersect_y = intersect_segments(&source_cuboid.y, &target.y)?;
     let intersect_z = intersect_segments(&source_cuboid.z, &target.z)?;
@@ -287,7 +299,7 @@ fn intersect_cuboid(source_cuboid: &Cuboid, target: &Cuboid) -> Option<Cuboid> {
 
 fn find_intersecting_instructions(
     instruction: &Instruction,
-    list: &Vec<Instruction>,
+    list: &[Instruction],
 ) -> Vec<Instruction> {
     return list
         .iter()
@@ -300,7 +312,8 @@ fn find_intersecting_instructions(
         .collect();
 }
 
-fn count_cells(c: &Cuboid) -> u64 {
+/// Number of cubes in the cuboid.
+pub fn count_cells(c: &Cuboid) -> u64 {
     (c.x.end() - c.x.start() + 1) as u64
         * (c.y.end() - c.y.start() + 1) as u64
         * (c.z.end() - c.z.start() + 1) as u64
@@ -392,62 +405,76 @@ struct State {
     counting: u64,
 }
 
-pub fn puzzle(part: &Part, lines: &Vec<String>) {
-    let instructions = parse(lines).unwrap();
-    match part {
-        Part::Part1 => {
-            let mut grid =
-                AdaptativeGrid::new(&instructions.iter().map(|it| &it.cuboid).collect(), 50);
-            for instruction in &instructions {
-                grid.set(&instruction.cuboid, instruction.on);
-            }
+/// Cubes left on by the steps, tracking the overlaps with inclusion–exclusion.
+/// With `is_part1`, only the cubes within -50..=50 on every axis are considered.
+pub fn count_with_overlaps(instructions: &[Instruction], is_part1: bool) -> u64 {
+    let mut state = State {
+        already_managed_with_overlaps: Vec::with_capacity(instructions.len() * 3),
+        counting: 0,
+    };
+    let start = Instant::now();
+    for instruction in instructions {
+        apply_instruction(&mut state, instruction, is_part1).unwrap_or(());
+    }
+    if DEBUG {
+        println!(
+            "Result Version iso TS: {} done in {} ms with memory {}",
+            state.counting,
+            start.elapsed().as_millis(),
+            size_of::<Instruction>() * state.already_managed_with_overlaps.capacity()
+        );
+    }
+    return state.counting;
+}
 
-            let result = grid.count();
-            println!("Result Version {}", result);
+/// Cubes left on by the steps within -max..=max on every axis, using a compressed grid
+/// split on the cuboid bounds.
+pub fn count_with_grid(instructions: &[Instruction], max: i32) -> u64 {
+    let mut grid = AdaptativeGrid::new(&instructions.iter().map(|it| &it.cuboid).collect(), max);
+    for instruction in instructions {
+        grid.set(&instruction.cuboid, instruction.on);
+    }
+    let start = Instant::now();
+    let result = grid.count();
+    if DEBUG {
+        println!(
+            "Result Eval {}, counted in {} ms with memory of {} bytes",
+            result,
+            start.elapsed().as_millis(),
+            grid.memory_estimate()
+        );
+    }
+    return result;
+}
 
-            let mut state = State {
-                already_managed_with_overlaps: Vec::with_capacity(instructions.len() * 3),
-                counting: 0,
-            };
-            for instruction in instructions {
-                apply_instruction(&mut state, &instruction, true).unwrap_or(());
-            }
-            println!("Result Version iso TS: {}", state.counting);
+const DEBUG: bool = false;
+
+/// Reactor Reboot: cubes left on in the initialization region, then in the whole reactor.
+pub struct Day22;
+
+impl Solver for Day22 {
+    type Input = Vec<Instruction>;
+    type Output1 = u64;
+    type Output2 = u64;
+
+    fn parse(&self, input: &str) -> Result<Vec<Instruction>, AocError> {
+        return parse(input);
+    }
+
+    fn part1(&self, instructions: &Vec<Instruction>) -> Result<u64, AocError> {
+        let result = count_with_grid(instructions, 50);
+        if DEBUG {
+            count_with_overlaps(instructions, true);
         }
-        Part::Part2 => {
-            let mut grid = AdaptativeGrid::new(
-                &instructions.iter().map(|it| &it.cuboid).collect(),
-                i32::MAX - 1,
-            );
-            for instruction in &instructions {
-                grid.set(&instruction.cuboid, instruction.on);
-            }
-            let start = Instant::now();
-            let result = grid.count();
-            let duration = start.elapsed().as_millis();
-            println!(
-                "Result Eval {}, counted in {} ms with memory of {} bytes",
-                result,
-                duration,
-                grid.memory_estimate()
-            );
-
-            let mut state = State {
-                already_managed_with_overlaps: Vec::with_capacity(instructions.len()),
-                counting: 0,
-            };
-            let start2 = Instant::now();
+        return Ok(result);
+    }
 
-            for instruction in instructions {
-                apply_instruction(&mut state, &instruction, false).unwrap_or(());
-            }
-            println!(
-                "Result Version iso TS: {} done in {} ms with memory {}",
-                state.counting,
-                start2.elapsed().as_millis(),
-                size_of::<Instruction>() * state.already_managed_with_overlaps.capacity()
-            );
+    fn part2(&self, instructions: &Vec<Instruction>) -> Result<u64, AocError> {
+        let result = count_with_grid(instructions, i32::MAX - 1);
+        if DEBUG {
+            count_with_overlaps(instructions, false);
         }
+        return Ok(result);
     }
 }
 
@@ -1,6 +1,9 @@
+use crate::error::{AocError, ParseFailure};
+use crate::solver::Solver;
 use crate::utils::Part;
 use regex::{Captures, Regex};
 use std::collections::{BinaryHeap, HashMap};
+use std::fmt;
 use std::fmt::Write;
 
 /*****
@@ -156,7 +159,9 @@ fn pop_room_pod(world: World, room: u8) -> World {
     } else {
         0
     };
-    let new_data = current_data & (0xFF << (new_offset + 1) * 2) | new_offset;
+    // Popping from the deepest cell leaves no pod above it, the mask is then empty
+    let above_offset = 0xFFu8.checked_shl((new_offset as u32 + 1) * 2).unwrap_or(0);
+    let new_data = current_data & above_offset | new_offset;
     let new_data_offsetted = (new_data as u64) << (room * ROOM_DATA_NB_BITS);
     let not_full_bit_offsetted =
         (ROOM_METADATA_NOT_FULL_BIT_MASK as u64) << (room * ROOM_METADATA_NB_BITS) << ROOMS_OFFSET;
@@ -166,7 +171,7 @@ fn pop_room_pod(world: World, room: u8) -> World {
         return cleaned_world | new_data_offsetted | not_full_bit_offsetted;
     } else {
         for offset in new_offset + 1..=depth {
-            let pod_value = (new_data >> offset * 2) & 0b11;
+            let pod_value = (new_data >> (offset * 2)) & 0b11;
             if pod_value != room {
                 let cleaned_world = world & !(new_data_mask);
                 return cleaned_world | new_data_offsetted | not_full_bit_offsetted;
@@ -235,7 +240,13 @@ fn parse_amphipod(captures: &Captures, pos: usize) -> Result<u8, ParsingError> {
         })
 }
 
-fn parse_line(line: &str, parser: &Regex) -> Result<(u8, u8, u8, u8), ParsingError> {
+/// Amphipods of one burrow row, from room A to room D: 0 for Amber up to 3 for Desert.
+pub type RoomsRow = (u8, u8, u8, u8);
+
+const PART2_EXTRA_ROWS: [RoomsRow; 2] =
+    [(POD_D, POD_C, POD_B, POD_A), (POD_D, POD_B, POD_A, POD_C)];
+
+fn parse_line(line: &str, parser: &Regex) -> Result<RoomsRow, ParsingError> {
     let captures = parser.captures(line).ok_or(ParsingError::BadFormat)?;
     let a = parse_amphipod(&captures, 1)?;
     let b = parse_amphipod(&captures, 2)?;
@@ -244,22 +255,46 @@ fn parse_line(line: &str, parser: &Regex) -> Result<(u8, u8, u8, u8), ParsingErr
     return Ok((a, b, c, d));
 }
 
-fn parse_pods(lines: &Vec<String>) -> Result<Vec<(u8, u8, u8, u8)>, ParsingError> {
+impl fmt::Display for ParsingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParsingError::BadFormat => write!(f, "not a row of amphipods A to D"),
+        }
+    }
+}
+
+impl ParseFailure for ParsingError {}
+
+/// Reads the amphipod rows of the burrow diagram, from the top row to the bottom one.
+pub fn parse_pods(input: &str) -> Result<Vec<RoomsRow>, AocError> {
     let parser: Regex =
         Regex::new(r"^..#(\w)#(\w)#(\w)#(\w)#.?.?$").or(Err(ParsingError::BadFormat))?;
+    let lines: Vec<&str> = input.lines().collect();
 
-    return lines
+    let pods = lines
         .iter()
+        .enumerate()
         .skip(2)
-        .take(lines.len() - 3)
-        .map(|line| parse_line(line, &parser))
-        .collect();
+        .take(lines.len().saturating_sub(3))
+        .map(|(index, line)| {
+            parse_line(line, &parser).map_err(|error| AocError::from(error).at_line(index + 1))
+        })
+        .collect::<Result<Vec<RoomsRow>, AocError>>()?;
+    if pods.is_empty() {
+        return Err(AocError::parse("no amphipod row in the burrow"));
+    }
+    return Ok(pods);
 }
 
-fn parse(lines: &Vec<String>, empty_world: u64) -> Result<World, ParsingError> {
-    let mut world: World = empty_world;
+/// Inserts the two rows hidden in the folded diagram of part 2.
+pub fn unfold_pods(pods: &[RoomsRow]) -> Vec<RoomsRow> {
+    let mut unfolded = pods.to_vec();
+    unfolded.splice(1..1, PART2_EXTRA_ROWS);
+    return unfolded;
+}
 
-    let pods = parse_pods(lines)?;
+fn build_world(pods: &[RoomsRow], empty_world: u64) -> World {
+    let mut world: World = empty_world;
 
     pods.iter().rev().for_each(|(a, b, c, d)| {
         world = set_room_with_pod(world, POD_A, *a);
@@ -268,7 +303,7 @@ fn parse(lines: &Vec<String>, empty_world: u64) -> Result<World, ParsingError> {
         world = set_room_with_pod(world, POD_D, *d);
     });
 
-    return Ok(world);
+    return world;
 }
 
 fn init_world(part: &Part) -> World {
@@ -302,7 +337,7 @@ impl Ord for WorldToEvaluate {
 
 impl PartialOrd for WorldToEvaluate {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        other.estimated_cost.partial_cmp(&self.estimated_cost)
+        Some(self.cmp(other))
     }
 }
 
@@ -376,8 +411,7 @@ fn build_world_to_evaluate(
     if world_ctxt.world & mask != 0 {
         return None;
     }
-    let new_unitary_cost = new_unitary_cost;
-    let new_estimated_cost = new_unitary_cost as i32
+    let new_estimated_cost = new_unitary_cost
         + if is_to_hallway {
             calc_effective_cost_info_from_hallway(pos, pod)
                 - calc_effective_cost_info_between_room(depth, room, pod)
@@ -399,7 +433,7 @@ fn calc_next_worlds(world_ctxt: &WorldToEvaluate) -> Vec<WorldToEvaluate> {
     for (pod, pos) in get_hallway_used_cells(world) {
         if let Some(depth) = get_room_available_for_set_with_depth(world, pod) {
             if let Some(world_built) =
-                build_world_to_evaluate(&world_ctxt, false, pod, pod, depth, pos)
+                build_world_to_evaluate(world_ctxt, false, pod, pod, depth, pos)
             {
                 result.push(world_built);
             }
@@ -410,7 +444,7 @@ fn calc_next_worlds(world_ctxt: &WorldToEvaluate) -> Vec<WorldToEvaluate> {
         if let Some((pod, depth)) = get_room_movable_pod_and_depth(world, room) {
             for pos in H_POS_0..=H_POS_10 {
                 if let Some(world_built) =
-                    build_world_to_evaluate(&world_ctxt, true, pod, room, depth, pos)
+                    build_world_to_evaluate(world_ctxt, true, pod, room, depth, pos)
                 {
                     result.push(world_built);
                 }
@@ -516,11 +550,7 @@ fn rebuild_history(
 }
 
 fn abs_diff(a: u8, b: u8) -> u8 {
-    if a > b {
-        a - b
-    } else {
-        b - a
-    }
+    a.abs_diff(b)
 }
 
 fn calc_room_to_hallway_pos(room: u8) -> u8 {
@@ -574,12 +604,12 @@ fn solve(start_world: World) -> Option<i32> {
             println!("Processing \n{}", world_to_string(next.world));
         }
         if is_world_finished(next.world) {
-            println!(
-                "Noeuds traités {} avec cout estimé {}",
-                processed.len(),
-                next.estimated_cost
-            );
             if DEBUG {
+                println!(
+                    "Noeuds traités {} avec cout estimé {}",
+                    processed.len(),
+                    next.estimated_cost
+                );
                 rebuild_history(&next, &processed);
             }
 
@@ -598,29 +628,42 @@ fn solve(start_world: World) -> Option<i32> {
     return None;
 }
 
-pub fn puzzle(part: &Part, lines: &Vec<String>) {
-    let effective_lines = if let Part::Part1 = part {
-        lines.clone()
-    } else {
-        let mut new_lines = lines.clone();
-        new_lines.insert(3, String::from("  #D#C#B#A#"));
-        new_lines.insert(4, String::from("  #D#B#A#C#"));
-        new_lines
+/// Least energy to organize burrows of depth 2 or 4, `None` for any other depth.
+pub fn least_energy(pods: &[RoomsRow]) -> Option<i32> {
+    let part = match pods.len() {
+        2 => Part::Part1,
+        4 => Part::Part2,
+        _ => return None,
     };
-    let start_world = parse(&effective_lines, init_world(part)).unwrap();
+    let start_world = build_world(pods, init_world(&part));
     if DEBUG {
         println!("{}", world_to_string(start_world));
     }
+    return solve(start_world);
+}
 
-    match part {
-        Part::Part1 => {
-            let res = solve(start_world).unwrap();
-            println!("Result {}", res);
-        }
-        Part::Part2 => {
-            let res = solve(start_world).unwrap();
-            println!("Result {}", res);
-        }
+/// Amphipod: least energy to organize the burrow, then the unfolded burrow.
+/// The burrow is encoded in a single `u64`, see [`Day23V2`](crate::day23_2::Day23V2) for
+/// another encoding.
+pub struct Day23;
+
+impl Solver for Day23 {
+    type Input = Vec<RoomsRow>;
+    type Output1 = i32;
+    type Output2 = i32;
+
+    fn parse(&self, input: &str) -> Result<Vec<RoomsRow>, AocError> {
+        return parse_pods(input);
+    }
+
+    fn part1(&self, pods: &Vec<RoomsRow>) -> Result<i32, AocError> {
+        return least_energy(pods)
+            .ok_or_else(|| AocError::no_solution("burrow cannot be organized"));
+    }
+
+    fn part2(&self, pods: &Vec<RoomsRow>) -> Result<i32, AocError> {
+        return least_energy(&unfold_pods(pods))
+            .ok_or_else(|| AocError::no_solution("burrow cannot be organized"));
     }
 }
 
@@ -824,6 +867,6 @@ mod tests {
         world = set_room_with_pod(world, POD_D, POD_D);
         world = set_room_with_pod(world, POD_D, POD_D);
 
-        assert_eq!(is_world_finished(world), true);
+        assert!(is_world_finished(world));
     }
 }
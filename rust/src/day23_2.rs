@@ -1,8 +1,9 @@
-use crate::utils::Part;
-use regex::{Captures, Regex};
-use std::collections::{BinaryHeap};
-use std::fmt::Write;
+use crate::day23::{parse_pods, unfold_pods, RoomsRow};
+use crate::error::AocError;
+use crate::solver::Solver;
 use hashbrown::HashMap;
+use std::collections::BinaryHeap;
+use std::fmt::Write;
 
 #[derive(PartialEq, Eq, Hash)]
 struct World {
@@ -78,13 +79,13 @@ fn calc_cell_value(pod: u8) -> u8 {
 fn set_room_with_pod(world: &mut World, room: u8, depth: u8, pod: u8) {
     let curr_room = &mut world.rooms[room as usize];
     let new_value = (calc_cell_value(pod) as u16) << (depth * 3 + 4);
-    *curr_room = *curr_room | new_value;
+    *curr_room |= new_value;
 }
 
 fn clear_room_pod(world: &mut World, room: u8, depth: u8) {
     let curr_room = &mut world.rooms[room as usize];
     let mask: u16 = 0b1 << depth | 0b111 << (depth * 3 + 4);
-    *curr_room = *curr_room & !mask;
+    *curr_room &= !mask;
 }
 
 fn set_hallway_pod(world: &mut World, pos: u8, pod: u8) {
@@ -92,7 +93,7 @@ fn set_hallway_pod(world: &mut World, pos: u8, pod: u8) {
 }
 
 fn clear_hallway_pod(world: &mut World, pos: u8) {
-    world.hallway = world.hallway & !(0b11 << HALLWAY_SHIFTS[pos as usize] | (1 << pos));
+    world.hallway &= !(0b11 << HALLWAY_SHIFTS[pos as usize] | (1 << pos));
 }
 
 fn is_hallway_move_possible_excluded(
@@ -138,48 +139,7 @@ fn get_hallway_movable_cells(world: &World) -> Vec<(u8, u8)> {
         .collect()
 }
 
-#[derive(Debug)]
-enum ParsingError {
-    BadFormat,
-}
-
-fn parse_amphipod(captures: &Captures, pos: usize) -> Result<u8, ParsingError> {
-    captures
-        .get(pos)
-        .ok_or(ParsingError::BadFormat)
-        .and_then(|v| match v.as_str() {
-            "A" => Ok(POD_A),
-            "B" => Ok(POD_B),
-            "C" => Ok(POD_C),
-            "D" => Ok(POD_D),
-            _ => Err(ParsingError::BadFormat),
-        })
-}
-
-fn parse_line(line: &str, parser: &Regex) -> Result<(u8, u8, u8, u8), ParsingError> {
-    let captures = parser.captures(line).ok_or(ParsingError::BadFormat)?;
-    let a = parse_amphipod(&captures, 1)?;
-    let b = parse_amphipod(&captures, 2)?;
-    let c = parse_amphipod(&captures, 3)?;
-    let d = parse_amphipod(&captures, 4)?;
-    return Ok((a, b, c, d));
-}
-
-fn parse_pods(lines: &Vec<String>) -> Result<Vec<(u8, u8, u8, u8)>, ParsingError> {
-    let parser: Regex =
-        Regex::new(r"^..#(\w)#(\w)#(\w)#(\w)#.?.?$").or(Err(ParsingError::BadFormat))?;
-
-    return lines
-        .iter()
-        .skip(2)
-        .take(lines.len() - 3)
-        .map(|line| parse_line(line, &parser))
-        .collect();
-}
-
-fn parse(lines: &Vec<String>, target_world: &mut World) -> Result<(), ParsingError> {
-    let pods = parse_pods(lines)?;
-
+fn fill_world(pods: &[RoomsRow], target_world: &mut World) {
     pods.iter()
         .enumerate()
         .rev()
@@ -189,8 +149,6 @@ fn parse(lines: &Vec<String>, target_world: &mut World) -> Result<(), ParsingErr
             set_room_with_pod_for_init(target_world, POD_C, *c, depth as u8);
             set_room_with_pod_for_init(target_world, POD_D, *d, depth as u8);
         });
-
-    return Ok(());
 }
 
 fn default_room(room: u8, max_depth: u8) -> u16 {
@@ -230,16 +188,12 @@ impl Ord for WorldToEvaluate {
 
 impl PartialOrd for WorldToEvaluate {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        other.estimated_cost.partial_cmp(&self.estimated_cost)
+        Some(self.cmp(other))
     }
 }
 
 fn abs_diff(a: u8, b: u8) -> u8 {
-    if a > b {
-        a - b
-    } else {
-        b - a
-    }
+    a.abs_diff(b)
 }
 
 fn calc_cost_info(depth: u8, room: u8, pos: u8) -> u32 {
@@ -268,8 +222,8 @@ fn apply_move(world: &World, move_data: &Move) -> (World, u32, i32) {
         hallway: world.hallway,
         rooms: world.rooms,
     };
-    match move_data {
-        &Move::ToHallway {
+    match *move_data {
+        Move::ToHallway {
             depth,
             pod,
             pos,
@@ -287,7 +241,7 @@ fn apply_move(world: &World, move_data: &Move) -> (World, u32, i32) {
                 effective_cost_offset * (COST[pod as usize] as i32),
             );
         }
-        &Move::ToRoom {
+        Move::ToRoom {
             depth,
             room,
             pod,
@@ -401,7 +355,6 @@ fn hallway_to_str(world: &World) -> String {
         &mut h_str,
         "#{}#",
         (0..=H_POS_MAX)
-            .into_iter()
             .map(|pos| if world.hallway & (0b1 << pos) == 0 {
                 '.'
             } else {
@@ -525,8 +478,8 @@ fn solve(start_world: World, max_depth: u8) -> Option<u32> {
             println!("Processing \n{}", world_to_string(&next.world, max_depth));
         }
         if is_world_finished(&next.world) {
-            println!("Noeuds traités {}", processed.len());
             if DEBUG {
+                println!("Noeuds traités {}", processed.len());
                 rebuild_history(&next, &processed, max_depth);
             }
             return Some(next.cost);
@@ -547,31 +500,48 @@ fn solve(start_world: World, max_depth: u8) -> Option<u32> {
     return None;
 }
 
-pub fn puzzle(part: &Part, lines: &Vec<String>) {
-    let depth: u8 = if let Part::Part1 = part { 1 } else { 3 };
+/// Least energy to organize the burrow, `depth` being the index of the deepest room cell:
+/// 1 for rooms of 2 amphipods, 3 for rooms of 4.
+pub fn solve_with_depth(pods: &[RoomsRow], depth: u8) -> Result<u32, AocError> {
+    if depth != 1 && depth != 3 {
+        return Err(AocError::parse(format!(
+            "rooms of {} amphipods are not supported",
+            depth as usize + 1
+        )));
+    }
+    if pods.len() != depth as usize + 1 {
+        return Err(AocError::parse(format!(
+            "expected {} amphipod rows, found {}",
+            depth as usize + 1,
+            pods.len()
+        )));
+    }
     let mut world = init_world(depth);
-    let effective_lines = if let Part::Part1 = part {
-        lines.clone()
-    } else {
-        let mut new_lines = lines.clone();
-        new_lines.insert(3, String::from("  #D#C#B#A#"));
-        new_lines.insert(4, String::from("  #D#B#A#C#"));
-        new_lines
-    };
-    parse(&effective_lines, &mut world).unwrap();
+    fill_world(pods, &mut world);
     if DEBUG {
         println!("{}", world_to_string(&world, depth));
     }
+    return solve(world, depth).ok_or_else(|| AocError::no_solution("burrow cannot be organized"));
+}
 
-    match part {
-        Part::Part1 => {
-            let res = solve(world, depth).unwrap();
-            println!("Result {}", res);
-        }
-        Part::Part2 => {
-            let res = solve(world, depth).unwrap();
-            println!("Result {}", res);
-        }
+/// Amphipod with a burrow encoding supporting any room depth.
+pub struct Day23V2;
+
+impl Solver for Day23V2 {
+    type Input = Vec<RoomsRow>;
+    type Output1 = u32;
+    type Output2 = u32;
+
+    fn parse(&self, input: &str) -> Result<Vec<RoomsRow>, AocError> {
+        return parse_pods(input);
+    }
+
+    fn part1(&self, pods: &Vec<RoomsRow>) -> Result<u32, AocError> {
+        return solve_with_depth(pods, 1);
+    }
+
+    fn part2(&self, pods: &Vec<RoomsRow>) -> Result<u32, AocError> {
+        return solve_with_depth(&unfold_pods(pods), 3);
     }
 }
 
@@ -614,7 +584,7 @@ mod tests {
         );
         assert_eq!(
             world.rooms[1],
-            0b011 << 13 | 0b011 << 10 | 0b011 << 7 | 0b011 << 4 | 0b0000
+            (0b011 << 13 | 0b011 << 10 | 0b011 << 7 | 0b011 << 4)
         );
     }
 
@@ -637,8 +607,8 @@ mod tests {
             },
         );
         assert_eq!(get_hallway_movable_cells(&world), vec![]);
-        assert_eq!(is_room_available(&world, POD_A), false);
-        assert_eq!(is_hallway_move_possible_included(&world, 0, 3), false);
+        assert!(!is_room_available(&world, POD_A));
+        assert!(!is_hallway_move_possible_included(&world, 0, 3));
     }
 
     #[test]
@@ -660,8 +630,24 @@ mod tests {
         );
         assert_eq!(
             world.rooms[1],
-            0b011 << 13 | 0b011 << 10 | 0b011 << 7 | 0b011 << 4 | 0b0000
+            (0b011 << 13 | 0b011 << 10 | 0b011 << 7 | 0b011 << 4)
+        );
+    }
+
+    #[test]
+    fn test_solve_checks_the_rows() {
+        let burrow = "#############\n#...........#\n###B#C#B#D###\n  #A#D#C#A#\n  #########\n";
+        let pods = parse_pods(burrow).unwrap();
+        assert_eq!(
+            Day23V2
+                .part1(&unfold_pods(&pods))
+                .err()
+                .map(|error| error.to_string()),
+            Some(String::from(
+                "Parse error: expected 2 amphipod rows, found 4"
+            ))
         );
+        assert!(solve_with_depth(&pods, 2).is_err());
     }
 
     #[test]
@@ -0,0 +1,134 @@
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum AocError {
+    Io {
+        path: String,
+        source: io::Error,
+    },
+    Parse {
+        file: Option<String>,
+        line: Option<usize>,
+        reason: String,
+    },
+    NoSolution {
+        line: Option<usize>,
+        reason: String,
+    },
+}
+
+impl AocError {
+    pub fn parse<R: fmt::Display>(reason: R) -> AocError {
+        AocError::Parse {
+            file: None,
+            line: None,
+            reason: reason.to_string(),
+        }
+    }
+
+    pub fn no_solution<R: fmt::Display>(reason: R) -> AocError {
+        AocError::NoSolution {
+            line: None,
+            reason: reason.to_string(),
+        }
+    }
+
+    // Line numbers are 1-based, as displayed by editors
+    pub fn at_line(self, line_number: usize) -> AocError {
+        match self {
+            AocError::Parse { file, reason, .. } => AocError::Parse {
+                file,
+                line: Some(line_number),
+                reason,
+            },
+            AocError::NoSolution { reason, .. } => AocError::NoSolution {
+                line: Some(line_number),
+                reason,
+            },
+            other => other,
+        }
+    }
+
+    pub fn in_file(self, path: &str) -> AocError {
+        match self {
+            AocError::Parse { line, reason, .. } => AocError::Parse {
+                file: Some(String::from(path)),
+                line,
+                reason,
+            },
+            other => other,
+        }
+    }
+}
+
+/// Reason why a day cannot parse its input, its message becomes the reason of
+/// [`AocError::Parse`].
+pub trait ParseFailure: fmt::Display {}
+
+impl<E: ParseFailure> From<E> for AocError {
+    fn from(error: E) -> Self {
+        AocError::parse(error)
+    }
+}
+
+impl fmt::Display for AocError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AocError::Io { path, source } => write!(f, "Cannot read {}: {}", path, source),
+            AocError::Parse { file, line, reason } => {
+                write!(f, "Parse error")?;
+                if let Some(file) = file {
+                    write!(f, " in {}", file)?;
+                }
+                if let Some(line) = line {
+                    write!(f, " at line {}", line)?;
+                }
+                write!(f, ": {}", reason)
+            }
+            AocError::NoSolution { line, reason } => {
+                write!(f, "No solution found")?;
+                if let Some(line) = line {
+                    write!(f, " at line {}", line)?;
+                }
+                write!(f, ": {}", reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AocError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AocError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_error_context() {
+        let error = AocError::parse("BadNumber")
+            .at_line(3)
+            .in_file("./data/day_18.txt");
+        assert_eq!(
+            error.to_string(),
+            "Parse error in ./data/day_18.txt at line 3: BadNumber"
+        );
+    }
+
+    #[test]
+    fn test_context_ignored_for_other_errors() {
+        let error = AocError::no_solution("empty queue").in_file("./data/day_15.txt");
+        assert_eq!(error.to_string(), "No solution found: empty queue");
+        let error = AocError::no_solution("above the surface").at_line(3);
+        assert_eq!(
+            error.to_string(),
+            "No solution found at line 3: above the surface"
+        );
+    }
+}
@@ -0,0 +1,97 @@
+use crate::solver::Answer;
+use crate::utils::Part;
+use std::fmt;
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ExpectedAnswers {
+    part1: Option<String>,
+    part2: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Status {
+    Pass,
+    Fail { expected: String },
+    Unknown,
+    Error(String),
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Status::Pass => write!(f, "PASS"),
+            Status::Fail { expected } => write!(f, "FAIL (expected {})", expected),
+            Status::Unknown => write!(f, "UNKNOWN"),
+            Status::Error(message) => write!(f, "ERROR {}", message),
+        }
+    }
+}
+
+impl ExpectedAnswers {
+    pub fn get(&self, part: &Part) -> Option<&String> {
+        return match part {
+            Part::Part1 => self.part1.as_ref(),
+            Part::Part2 => self.part2.as_ref(),
+        };
+    }
+
+    pub fn check(&self, part: &Part, answer: &Answer) -> Status {
+        return match self.get(part) {
+            None => Status::Unknown,
+            Some(expected) if *expected == answer.to_string() => Status::Pass,
+            Some(expected) => Status::Fail {
+                expected: expected.clone(),
+            },
+        };
+    }
+}
+
+// One `partN: <answer>` entry per line, blank lines and `#` comments are ignored
+pub fn parse_expected(content: &str) -> ExpectedAnswers {
+    let mut expected = ExpectedAnswers::default();
+    for line in content.lines().map(|line| line.trim()) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some((key, value)) = line.split_once(':') {
+            let value = Some(String::from(value.trim()));
+            match key.trim() {
+                "part1" => expected.part1 = value,
+                "part2" => expected.part2 = value,
+                _ => {}
+            }
+        }
+    }
+    return expected;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_expected() {
+        let expected = parse_expected("# day 1\npart1: 7\n\n part2 :5 \n");
+        assert_eq!(expected.get(&Part::Part1), Some(&String::from("7")));
+        assert_eq!(expected.get(&Part::Part2), Some(&String::from("5")));
+    }
+
+    #[test]
+    fn test_check_status() {
+        let expected = parse_expected("part1: 7");
+        assert_eq!(
+            expected.check(&Part::Part1, &Answer::Unsigned(7)),
+            Status::Pass
+        );
+        assert_eq!(
+            expected.check(&Part::Part1, &Answer::Unsigned(8)),
+            Status::Fail {
+                expected: String::from("7")
+            }
+        );
+        assert_eq!(
+            expected.check(&Part::Part2, &Answer::Unsigned(8)),
+            Status::Unknown
+        );
+    }
+}
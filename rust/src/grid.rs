@@ -0,0 +1,202 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// Cell of a grid, `x` is the column and `y` the row, both from the top left corner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Point {
+    pub x: usize,
+    pub y: usize,
+}
+
+/// Cells reachable in one step: the 4 sharing a side, or the 8 sharing a side or a corner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Connectivity {
+    Four,
+    Eight,
+}
+
+const SIDES: [(isize, isize); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+const SIDES_AND_CORNERS: [(isize, isize); 8] = [
+    (1, 0),
+    (0, 1),
+    (-1, 0),
+    (0, -1),
+    (1, 1),
+    (-1, 1),
+    (-1, -1),
+    (1, -1),
+];
+
+/// Shape of the grid to search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Grid {
+    pub width: usize,
+    pub height: usize,
+    pub connectivity: Connectivity,
+}
+
+impl Grid {
+    pub fn new(width: usize, height: usize, connectivity: Connectivity) -> Grid {
+        return Grid {
+            width,
+            height,
+            connectivity,
+        };
+    }
+
+    pub fn contains(&self, point: Point) -> bool {
+        return point.x < self.width && point.y < self.height;
+    }
+
+    pub fn neighbours(&self, point: Point) -> impl Iterator<Item = Point> + '_ {
+        let offsets: &[(isize, isize)] = match self.connectivity {
+            Connectivity::Four => &SIDES,
+            Connectivity::Eight => &SIDES_AND_CORNERS,
+        };
+        return offsets.iter().filter_map(move |(dx, dy)| {
+            let neighbour = Point {
+                x: point.x.checked_add_signed(*dx)?,
+                y: point.y.checked_add_signed(*dy)?,
+            };
+            self.contains(neighbour).then_some(neighbour)
+        });
+    }
+
+    fn index(&self, point: Point) -> usize {
+        return point.y * self.width + point.x;
+    }
+
+    fn point(&self, index: usize) -> Point {
+        return Point {
+            x: index % self.width,
+            y: index / self.width,
+        };
+    }
+}
+
+/// Cheapest route found by [`search`], from the start to the goal included.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Route {
+    pub cost: u64,
+    pub cells: Vec<Point>,
+}
+
+/// No estimate, the search behaves as Dijkstra's algorithm.
+pub fn dijkstra(_from: Point, _goal: Point) -> u64 {
+    return 0;
+}
+
+/// Number of steps to the goal with [`Connectivity::Four`].
+pub fn manhattan(from: Point, goal: Point) -> u64 {
+    return (from.x.abs_diff(goal.x) + from.y.abs_diff(goal.y)) as u64;
+}
+
+/// Number of steps to the goal with [`Connectivity::Eight`].
+pub fn chebyshev(from: Point, goal: Point) -> u64 {
+    return from.x.abs_diff(goal.x).max(from.y.abs_diff(goal.y)) as u64;
+}
+
+/// Cheapest route from `start` to `goal`, `None` if the goal cannot be reached.
+///
+/// `cost(from, to)` is the cost of stepping between two neighbours, `None` when the step is
+/// not allowed. `heuristic(cell, goal)` estimates the remaining cost, the route is the
+/// cheapest one as long as it never overestimates: [`manhattan`] and [`chebyshev`] count
+/// steps, so they only fit when every step costs at least 1.
+pub fn search<C, H>(grid: &Grid, start: Point, goal: Point, cost: C, heuristic: H) -> Option<Route>
+where
+    C: Fn(Point, Point) -> Option<u64>,
+    H: Fn(Point, Point) -> u64,
+{
+    if !grid.contains(start) || !grid.contains(goal) {
+        return None;
+    }
+    let cells = grid.width * grid.height;
+    let mut best_cost = vec![u64::MAX; cells];
+    let mut previous = vec![usize::MAX; cells];
+    let mut queue = BinaryHeap::new();
+    best_cost[grid.index(start)] = 0;
+    queue.push(Reverse((heuristic(start, goal), 0, grid.index(start))));
+
+    while let Some(Reverse((_, cost_from_start, index))) = queue.pop() {
+        // Cells are queued again when a cheaper way to them is found, skip the stale entries
+        if cost_from_start > best_cost[index] {
+            continue;
+        }
+        let point = grid.point(index);
+        if point == goal {
+            let mut route = vec![point];
+            let mut current = index;
+            while previous[current] != usize::MAX {
+                current = previous[current];
+                route.push(grid.point(current));
+            }
+            route.reverse();
+            return Some(Route {
+                cost: cost_from_start,
+                cells: route,
+            });
+        }
+        for neighbour in grid.neighbours(point) {
+            let Some(step) = cost(point, neighbour) else {
+                continue;
+            };
+            let next_cost = cost_from_start + step;
+            let next = grid.index(neighbour);
+            if next_cost < best_cost[next] {
+                best_cost[next] = next_cost;
+                previous[next] = index;
+                queue.push(Reverse((
+                    next_cost + heuristic(neighbour, goal),
+                    next_cost,
+                    next,
+                )));
+            }
+        }
+    }
+    return None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WALLS: [&str; 4] = ["..#.", "..#.", "..#.", "...."];
+
+    fn open(_from: Point, to: Point) -> Option<u64> {
+        return (WALLS[to.y].as_bytes()[to.x] == b'.').then_some(1);
+    }
+
+    #[test]
+    fn test_search_around_walls() {
+        let start = Point { x: 0, y: 0 };
+        let goal = Point { x: 3, y: 0 };
+        let four = Grid::new(4, 4, Connectivity::Four);
+        let route = search(&four, start, goal, open, manhattan).unwrap();
+        assert_eq!(route.cost, 9);
+        assert_eq!(route.cells.len(), 10);
+        assert_eq!(
+            search(&four, start, goal, open, dijkstra).map(|route| route.cost),
+            Some(9)
+        );
+
+        let eight = Grid::new(4, 4, Connectivity::Eight);
+        let route = search(&eight, start, goal, open, chebyshev).unwrap();
+        assert_eq!(route.cost, 6);
+        assert_eq!(route.cells[3], Point { x: 2, y: 3 });
+    }
+
+    #[test]
+    fn test_unreachable_goal() {
+        let grid = Grid::new(4, 4, Connectivity::Four);
+        let blocked = |_: Point, to: Point| (to.y < 3).then_some(1);
+        let start = Point { x: 0, y: 0 };
+        assert_eq!(
+            search(&grid, start, Point { x: 3, y: 3 }, blocked, manhattan),
+            None
+        );
+        assert_eq!(
+            search(&grid, start, Point { x: 4, y: 0 }, open, manhattan),
+            None
+        );
+    }
+}
@@ -0,0 +1,388 @@
+use crate::error::AocError;
+use crate::expected::{parse_expected, ExpectedAnswers};
+use crate::utils::{Dataset, Part};
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock, PoisonError};
+
+pub const DATA_DIR_VAR: &str = "AOC_DATA_DIR";
+const DEFAULT_DATA_DIR: &str = "./data";
+
+/// Puzzle input, either held in memory or streamed line by line from a reader.
+pub enum Input<'a> {
+    Text(&'a str),
+    Stream(Box<dyn BufRead + 'a>),
+}
+
+impl Input<'_> {
+    /// Calls `f` with the 1-based number and the content of each line, without its line ending.
+    /// A stream reuses a single buffer, so memory does not grow with the input size.
+    pub fn try_for_each_line<F>(self, mut f: F) -> Result<(), AocError>
+    where
+        F: FnMut(usize, &str) -> Result<(), AocError>,
+    {
+        match self {
+            Input::Text(text) => {
+                for (index, line) in text.lines().enumerate() {
+                    f(index + 1, line)?;
+                }
+            }
+            Input::Stream(mut reader) => {
+                let mut buffer = String::new();
+                let mut line_number = 0;
+                loop {
+                    buffer.clear();
+                    let read = reader
+                        .read_line(&mut buffer)
+                        .map_err(|source| AocError::Io {
+                            path: String::from("<stream>"),
+                            source,
+                        })?;
+                    if read == 0 {
+                        break;
+                    }
+                    line_number += 1;
+                    let line = buffer.strip_suffix('\n').unwrap_or(&buffer);
+                    f(line_number, line.strip_suffix('\r').unwrap_or(line))?;
+                }
+            }
+        }
+        return Ok(());
+    }
+}
+
+fn read_to_string<R: Read>(mut reader: R, location: &str) -> Result<String, AocError> {
+    let mut content = String::new();
+    reader
+        .read_to_string(&mut content)
+        .map_err(|source| AocError::Io {
+            path: String::from(location),
+            source,
+        })?;
+    return Ok(content);
+}
+
+fn open_file(path: &Path) -> Result<File, AocError> {
+    return File::open(path).map_err(|source| AocError::Io {
+        path: path.display().to_string(),
+        source,
+    });
+}
+
+/// Finds where the input of a day comes from: a file under the data root, stdin or inline text.
+pub struct InputResolver {
+    data_dir: PathBuf,
+    // Stdin can only be consumed once: either read on first use and shared by every selected
+    // day, or streamed to a single solver
+    stdin: OnceLock<String>,
+    stdin_reading: Mutex<()>,
+    stdin_streamed: AtomicBool,
+    examples_required: bool,
+}
+
+impl InputResolver {
+    /// Resolves files under `data_dir`, else under `$AOC_DATA_DIR`, else under `./data`.
+    pub fn new(data_dir: Option<&str>) -> InputResolver {
+        let data_dir = match data_dir {
+            Some(dir) => PathBuf::from(dir),
+            None => env::var_os(DATA_DIR_VAR)
+                .map(PathBuf::from)
+                .unwrap_or_else(|| PathBuf::from(DEFAULT_DATA_DIR)),
+        };
+        return InputResolver {
+            data_dir,
+            stdin: OnceLock::new(),
+            stdin_reading: Mutex::new(()),
+            stdin_streamed: AtomicBool::new(false),
+            examples_required: false,
+        };
+    }
+
+    /// With required examples, a day without any keeps [`Dataset::Examples`] to report it.
+    pub fn require_examples(mut self, required: bool) -> InputResolver {
+        self.examples_required = required;
+        return self;
+    }
+
+    /// Reads stdin upfront, so that reading errors are reported before solving anything.
+    pub fn read_stdin(self) -> Result<InputResolver, AocError> {
+        self.stdin_text()?;
+        return Ok(self);
+    }
+
+    fn stdin_text(&self) -> Result<&str, AocError> {
+        let _reading = self
+            .stdin_reading
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some(text) = self.stdin.get() {
+            return Ok(text);
+        }
+        if self.stdin_streamed.load(Ordering::Relaxed) {
+            return Err(stdin_streamed());
+        }
+        let text = read_to_string(io::stdin().lock(), "<stdin>")?;
+        return Ok(self.stdin.get_or_init(|| text));
+    }
+
+    fn examples_dir(&self, day: &u8) -> PathBuf {
+        return self.data_dir.join(format!("day_{}", day)).join("examples");
+    }
+
+    /// Names of the examples of the day, sorted. Examples are `day_<N>/examples/<name>.txt`,
+    /// or `<name>.part1.txt` and `<name>.part2.txt` when each part needs its own input.
+    pub fn examples(&self, day: &u8) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(self.examples_dir(day))
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .filter_map(|entry| {
+                        let file_name = entry.file_name().into_string().ok()?;
+                        let stem = file_name.strip_suffix(".txt")?;
+                        let name = stem
+                            .strip_suffix(".part1")
+                            .or_else(|| stem.strip_suffix(".part2"))
+                            .unwrap_or(stem);
+                        Some(String::from(name))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        names.sort();
+        names.dedup();
+        return names;
+    }
+
+    /// Replaces [`Dataset::Examples`] with every example found for the day.
+    pub fn expand(&self, day: &u8, data_sets: &[Dataset]) -> Vec<Dataset> {
+        return data_sets
+            .iter()
+            .flat_map(|data_set| match data_set {
+                Dataset::Examples => {
+                    let examples = self.examples(day);
+                    if examples.is_empty() && self.examples_required {
+                        return vec![Dataset::Examples];
+                    }
+                    examples.into_iter().map(Dataset::Example).collect()
+                }
+                other => vec![other.clone()],
+            })
+            .collect();
+    }
+
+    /// Error for an example that does not exist, other inputs fail when they are read.
+    pub fn missing(&self, day: &u8, data_set: &Dataset) -> Option<AocError> {
+        let (path, reason) = match data_set {
+            Dataset::Examples => (self.examples_dir(day), "no examples"),
+            Dataset::Example(name) if !self.examples(day).contains(name) => (
+                self.examples_dir(day).join(format!("{}.txt", name)),
+                "no such example",
+            ),
+            _ => return None,
+        };
+        return Some(AocError::Io {
+            path: path.display().to_string(),
+            source: io::Error::new(io::ErrorKind::NotFound, reason),
+        });
+    }
+
+    /// Path of the file of the dataset with the given extension, `None` for stdin and inline
+    /// inputs.
+    pub fn file_path(&self, day: &u8, data_set: &Dataset, extension: &str) -> Option<PathBuf> {
+        let suffix = match data_set {
+            Dataset::Test => String::from("_test"),
+            Dataset::Real => String::new(),
+            Dataset::Named(name) => format!("_{}", name),
+            Dataset::Example(name) => {
+                return Some(
+                    self.examples_dir(day)
+                        .join(format!("{}.{}", name, extension)),
+                )
+            }
+            Dataset::Examples | Dataset::Stdin | Dataset::Inline(_) => return None,
+        };
+        return Some(
+            self.data_dir
+                .join(format!("day_{}{}.{}", day, suffix, extension)),
+        );
+    }
+
+    fn input_path(&self, day: &u8, data_set: &Dataset, part: &Part) -> Option<PathBuf> {
+        if let Dataset::Example(name) = data_set {
+            let part_path =
+                self.examples_dir(day)
+                    .join(format!("{}.{}.txt", name, part_suffix(part)));
+            if part_path.exists() {
+                return Some(part_path);
+            }
+        }
+        return self.file_path(day, data_set, "txt");
+    }
+
+    /// Only examples may lack an input, when they target the other part.
+    pub fn has_input(&self, day: &u8, data_set: &Dataset, part: &Part) -> bool {
+        return match data_set {
+            Dataset::Example(_) => self
+                .input_path(day, data_set, part)
+                .is_some_and(|path| path.exists()),
+            _ => true,
+        };
+    }
+
+    /// Human readable origin of the input, used to give context to errors.
+    pub fn location(&self, day: &u8, data_set: &Dataset, part: &Part) -> String {
+        return match self.input_path(day, data_set, part) {
+            Some(path) => path.display().to_string(),
+            None => format!("<{}>", data_set.to_string().to_lowercase()),
+        };
+    }
+
+    /// Whole input of the part. Stdin is read once and shared by every day.
+    pub fn read(&self, day: &u8, data_set: &Dataset, part: &Part) -> Result<String, AocError> {
+        if let Some(path) = self.input_path(day, data_set, part) {
+            return read_to_string(open_file(&path)?, &path.display().to_string());
+        }
+        return match data_set {
+            Dataset::Inline(text) => Ok(text.clone()),
+            _ => self.stdin_text().map(String::from),
+        };
+    }
+
+    /// Input of the part as a stream: files are streamed, inputs already in memory are
+    /// borrowed. Stdin is streamed unless it was already read, and only once.
+    pub fn open<'a>(
+        &'a self,
+        day: &u8,
+        data_set: &'a Dataset,
+        part: &Part,
+    ) -> Result<Input<'a>, AocError> {
+        if let Some(path) = self.input_path(day, data_set, part) {
+            return Ok(Input::Stream(Box::new(BufReader::new(open_file(&path)?))));
+        }
+        if let Dataset::Inline(text) = data_set {
+            return Ok(Input::Text(text));
+        }
+        let _reading = self
+            .stdin_reading
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some(text) = self.stdin.get() {
+            return Ok(Input::Text(text));
+        }
+        if self.stdin_streamed.swap(true, Ordering::Relaxed) {
+            return Err(stdin_streamed());
+        }
+        return Ok(Input::Stream(Box::new(io::stdin().lock())));
+    }
+
+    /// Answers of the `.answers` file next to the input. Inputs that do not come from the data
+    /// root have none.
+    pub fn expected(&self, day: &u8, data_set: &Dataset) -> ExpectedAnswers {
+        return self
+            .file_path(day, data_set, "answers")
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|content| parse_expected(&content))
+            .unwrap_or_default();
+    }
+}
+
+fn stdin_streamed() -> AocError {
+    return AocError::Io {
+        path: String::from("<stdin>"),
+        source: io::Error::other("already streamed to another solver"),
+    };
+}
+
+fn part_suffix(part: &Part) -> &'static str {
+    return match part {
+        Part::Part1 => "part1",
+        Part::Part2 => "part2",
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collect_lines(input: Input) -> Vec<(usize, String)> {
+        let mut lines = vec![];
+        input
+            .try_for_each_line(|number, line| {
+                lines.push((number, String::from(line)));
+                Ok(())
+            })
+            .unwrap();
+        return lines;
+    }
+
+    #[test]
+    fn test_stream_lines() {
+        let expected = vec![
+            (1, String::from("a")),
+            (2, String::new()),
+            (3, String::from("b")),
+        ];
+        assert_eq!(collect_lines(Input::Text("a\n\nb\n")), expected);
+        assert_eq!(
+            collect_lines(Input::Stream(Box::new(io::Cursor::new("a\r\n\r\nb")))),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_file_path() {
+        let resolver = InputResolver::new(Some("/srv/aoc"));
+        assert_eq!(
+            resolver.file_path(&15, &Dataset::Named(String::from("big")), "txt"),
+            Some(PathBuf::from("/srv/aoc/day_15_big.txt"))
+        );
+        assert_eq!(
+            resolver.file_path(&1, &Dataset::Test, "answers"),
+            Some(PathBuf::from("/srv/aoc/day_1_test.answers"))
+        );
+        assert_eq!(
+            resolver.file_path(&16, &Dataset::Example(String::from("sum")), "answers"),
+            Some(PathBuf::from("/srv/aoc/day_16/examples/sum.answers"))
+        );
+        assert_eq!(resolver.file_path(&1, &Dataset::Stdin, "txt"), None);
+        assert_eq!(
+            resolver.location(&1, &Dataset::Stdin, &Part::Part1),
+            "<stdin>"
+        );
+    }
+
+    #[test]
+    fn test_missing_examples() {
+        let resolver = InputResolver::new(Some("data"));
+        let sum = Dataset::Example(String::from("sum"));
+        assert!(resolver.missing(&16, &sum).is_none());
+        let typo = Dataset::Example(String::from("typo"));
+        assert_eq!(
+            resolver.missing(&16, &typo).map(|error| error.to_string()),
+            Some(String::from(
+                "Cannot read data/day_16/examples/typo.txt: no such example"
+            ))
+        );
+        assert!(resolver.expand(&15, &[Dataset::Examples]).is_empty());
+        let resolver = resolver.require_examples(true);
+        assert_eq!(
+            resolver.expand(&15, &[Dataset::Examples]),
+            vec![Dataset::Examples]
+        );
+        assert!(resolver.missing(&15, &Dataset::Examples).is_some());
+    }
+
+    #[test]
+    fn test_inline_input() {
+        let resolver = InputResolver::new(None);
+        let data_set = Dataset::Inline(String::from("199\n200\n"));
+        assert_eq!(
+            resolver.read(&1, &data_set, &Part::Part2).unwrap(),
+            "199\n200\n"
+        );
+        assert_eq!(resolver.location(&1, &data_set, &Part::Part2), "<inline>");
+    }
+}
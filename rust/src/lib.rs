@@ -0,0 +1,27 @@
+//! Advent of Code 2021 solvers.
+//!
+//! Every `dayN` module exposes the parser and the algorithms of a day, plus a [`solver::Solver`]
+//! implementation. The other modules form the runner used by the `advent_of_code_2021` binary:
+//! input resolution, expected answers, reports and benchmarks.
+#![allow(clippy::needless_return)]
+
+pub mod bench;
+pub mod day1;
+pub mod day12;
+pub mod day15;
+pub mod day16;
+pub mod day18;
+pub mod day2;
+pub mod day22;
+pub mod day23;
+pub mod day23_2;
+pub mod error;
+pub mod expected;
+pub mod grid;
+pub mod input;
+pub mod memory;
+pub mod pool;
+pub mod registry;
+pub mod report;
+pub mod solver;
+pub mod utils;
@@ -1,23 +1,218 @@
-mod day1;
-mod day2;
-mod day12;
-mod day15;
-mod day16;
-mod day18;
-mod day22;
-mod day23;
-mod day23_2;
-mod utils;
+#![allow(clippy::needless_return)]
 
-fn main() {
-    utils::run_all(&1, &day1::puzzle, &utils::Active::False);
-    utils::run_all(&2, &day2::puzzle, &utils::Active::False);
-    utils::run_all(&12, &day12::puzzle, &utils::Active::False);
-    utils::run_all(&15, &day15::puzzle, &utils::Active::True);
-    utils::run_all(&16, &day16::puzzle, &utils::Active::False);
-    utils::run_all(&18, &day18::puzzle, &utils::Active::False);
-    utils::run_all(&22, &day22::puzzle, &utils::Active::False);
-    utils::run_all(&23, &day23::puzzle, &utils::Active::False);
-    utils::run_all(&23, &day23_2::puzzle, &utils::Active::False);
+mod cli;
+
+use advent_of_code_2021::error::AocError;
+use advent_of_code_2021::expected::Status;
+use advent_of_code_2021::input::InputResolver;
+use advent_of_code_2021::registry::{self, DayEntry};
+use advent_of_code_2021::report::{self, ReportFormat, Reporter};
+use advent_of_code_2021::utils::{self, Dataset};
+use advent_of_code_2021::{bench, memory, pool};
+use cli::{BenchOptions, CliError, Command, DaySelection, RunOptions, Selection};
+
+#[global_allocator]
+static GLOBAL: memory::TrackingAllocator = memory::TrackingAllocator;
+
+fn select_days(selection: &DaySelection) -> Result<Vec<&'static DayEntry>, CliError> {
+    return match selection {
+        DaySelection::All => Ok(registry::all_days().iter().collect()),
+        DaySelection::Days(days) => {
+            let mut entries = vec![];
+            for day in days {
+                let found = registry::find_day(*day);
+                if found.is_empty() {
+                    return Err(CliError::UnknownDay {
+                        day: *day,
+                        available: registry::available_days(),
+                    });
+                }
+                entries.extend(found);
+            }
+            Ok(entries)
+        }
+    };
+}
 
+// Stdin can only be consumed once. It is streamed when a single day can stream it, and read
+// upfront to be shared otherwise
+fn resolver(
+    selection: &Selection,
+    entries: &[&'static DayEntry],
+    stream: bool,
+) -> Result<InputResolver, AocError> {
+    // Examples asked for a given day must exist, not when they come with the defaults or --all
+    let resolver = InputResolver::new(selection.data_dir.as_deref()).require_examples(
+        selection.datasets_given && matches!(selection.days, DaySelection::Days(_)),
+    );
+    let streams_stdin = stream
+        && entries.len() == 1
+        && entries[0].puzzle.streams()
+        && selection
+            .datasets
+            .iter()
+            .filter(|data_set| **data_set == Dataset::Stdin)
+            .count()
+            == 1;
+    if selection.datasets.contains(&Dataset::Stdin) && !streams_stdin {
+        return resolver.read_stdin();
+    }
+    return Ok(resolver);
+}
+
+fn run(entries: &[&'static DayEntry], options: &RunOptions) -> Result<bool, AocError> {
+    let inputs = resolver(&options.selection, entries, options.stream)?;
+    let to_error = |source| AocError::Io {
+        path: options.output.clone().unwrap_or(String::from("<stdout>")),
+        source,
+    };
+    let mut reporter = Reporter::new(options.format, &options.output).map_err(to_error)?;
+    let mut statuses: Vec<Status> = vec![];
+    let parts = &options.selection.parts;
+    let mut flush = |records: &mut Vec<report::RunRecord>| -> Result<(), AocError> {
+        utils::sort_by_part(records, parts);
+        for record in records.drain(..) {
+            reporter.record(&record).map_err(to_error)?;
+            statuses.push(record.status);
+        }
+        return Ok(());
+    };
+
+    // One task per day and dataset, the records of a day are reported together once all done
+    let tasks: Vec<(usize, &DayEntry, Dataset)> = entries
+        .iter()
+        .enumerate()
+        .flat_map(|(index, entry)| {
+            inputs
+                .expand(&entry.day, &options.selection.datasets)
+                .into_iter()
+                .map(move |data_set| (index, *entry, data_set))
+        })
+        .collect();
+    let jobs = match options.jobs {
+        0 => pool::available_jobs(),
+        jobs => jobs,
+    };
+    let mut day_records = vec![];
+    let mut current_day = None;
+    pool::map_ordered(
+        jobs,
+        &tasks,
+        |(index, entry, data_set)| {
+            (
+                *index,
+                utils::run_dataset(entry, &inputs, parts, data_set, options.stream),
+            )
+        },
+        |(index, records)| {
+            if current_day != Some(index) {
+                flush(&mut day_records)?;
+                current_day = Some(index);
+            }
+            day_records.extend(records);
+            return Ok::<(), AocError>(());
+        },
+    )?;
+    flush(&mut day_records)?;
+    let count = |pred: fn(&Status) -> bool| statuses.iter().filter(|s| pred(s)).count();
+    let failed = count(|s| matches!(s, Status::Fail { .. }));
+    let errors = count(|s| matches!(s, Status::Error(_)));
+    let summary = format!(
+        "Summary: {} passed, {} failed, {} unknown, {} errors",
+        count(|s| matches!(s, Status::Pass)),
+        failed,
+        count(|s| matches!(s, Status::Unknown)),
+        errors
+    );
+    // Keep stdout parseable when it carries a machine-readable report
+    if options.format == ReportFormat::Text || options.output.is_some() {
+        println!("{}", summary);
+    } else {
+        eprintln!("{}", summary);
+    }
+    return Ok(failed == 0 && errors == 0);
+}
+
+fn bench(entries: &[&'static DayEntry], options: &BenchOptions) -> Result<bool, AocError> {
+    let inputs = resolver(&options.selection, entries, false)?;
+    let baseline = match &options.baseline {
+        Some(path) => bench::load_baseline(path)?,
+        None => bench::Baseline::new(),
+    };
+    let mut success = true;
+    let mut results = vec![];
+    for entry in entries {
+        for result in utils::bench_all(
+            entry,
+            &inputs,
+            &options.selection.parts,
+            &options.selection.datasets,
+            options.warmup,
+            options.iterations,
+        ) {
+            match result {
+                Ok(result) => {
+                    let regressions =
+                        bench::find_regressions(&result, &baseline, options.threshold);
+                    println!("{}", bench::to_text(&result, &regressions));
+                    success &= regressions.is_empty();
+                    results.push(result);
+                }
+                Err(error) => {
+                    eprintln!("[Day {}][{}] ERROR {}", entry.day, entry.name, error);
+                    success = false;
+                }
+            }
+        }
+    }
+    if let Some(path) = &options.save_baseline {
+        bench::save_baseline(path, &results)?;
+    }
+    return Ok(success);
+}
+
+fn report_error(result: Result<bool, AocError>) -> bool {
+    return match result {
+        Ok(success) => success,
+        Err(error) => {
+            eprintln!("Error: {}", error);
+            false
+        }
+    };
+}
+
+fn execute(command: Command) -> Result<bool, CliError> {
+    return match command {
+        Command::Run(options) => {
+            let entries = select_days(&options.selection.days)?;
+            Ok(report_error(run(&entries, &options)))
+        }
+        Command::Bench(options) => {
+            let entries = select_days(&options.selection.days)?;
+            Ok(report_error(bench(&entries, &options)))
+        }
+        Command::List => {
+            for entry in registry::all_days() {
+                println!("{:>2} {}", entry.day, entry.name);
+            }
+            Ok(true)
+        }
+        Command::Help => {
+            println!("{}", cli::USAGE);
+            Ok(true)
+        }
+    };
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match cli::parse_args(&args).and_then(execute) {
+        Ok(true) => {}
+        Ok(false) => std::process::exit(1),
+        Err(error) => {
+            eprintln!("Error: {}", error);
+            eprintln!("{}", cli::USAGE);
+            std::process::exit(2);
+        }
+    }
 }
@@ -0,0 +1,67 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+// Counters are per thread so that a measured run is not polluted by other threads.
+// Memory freed by another thread than the allocating one makes the figures an estimate.
+thread_local! {
+    static CURRENT: Cell<isize> = const { Cell::new(0) };
+    static PEAK: Cell<isize> = const { Cell::new(0) };
+}
+
+pub struct TrackingAllocator;
+
+fn track(delta: isize) {
+    let _ = CURRENT.try_with(|current| {
+        let value = current.get() + delta;
+        current.set(value);
+        let _ = PEAK.try_with(|peak| {
+            if value > peak.get() {
+                peak.set(value)
+            }
+        });
+    });
+}
+
+unsafe impl GlobalAlloc for TrackingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            track(layout.size() as isize);
+        }
+        return ptr;
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        track(-(layout.size() as isize));
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            track(layout.size() as isize);
+        }
+        return ptr;
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            track(new_size as isize - layout.size() as isize);
+        }
+        return new_ptr;
+    }
+}
+
+// Starts a new measurement window on the current thread and returns its baseline
+pub fn reset_peak() -> isize {
+    let current = CURRENT.with(|current| current.get());
+    PEAK.with(|peak| peak.set(current));
+    return current;
+}
+
+// Highest number of bytes allocated on the current thread above the baseline
+pub fn peak_since(baseline: isize) -> usize {
+    let peak = PEAK.with(|peak| peak.get());
+    return (peak - baseline).max(0) as usize;
+}
@@ -0,0 +1,90 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+pub fn available_jobs() -> usize {
+    return thread::available_parallelism()
+        .map(|jobs| jobs.get())
+        .unwrap_or(1);
+}
+
+/// Runs `work` on every task with up to `jobs` threads and hands the results to `consume`
+/// in the order of the tasks, as soon as all the previous ones are consumed.
+pub fn map_ordered<T, R, E, W, C>(
+    jobs: usize,
+    tasks: &[T],
+    work: W,
+    mut consume: C,
+) -> Result<(), E>
+where
+    T: Sync,
+    R: Send,
+    W: Fn(&T) -> R + Sync,
+    C: FnMut(R) -> Result<(), E>,
+{
+    if jobs <= 1 || tasks.len() <= 1 {
+        for task in tasks {
+            consume(work(task))?;
+        }
+        return Ok(());
+    }
+
+    let next_task = AtomicUsize::new(0);
+    return thread::scope(|scope| {
+        let (sender, receiver) = mpsc::channel();
+        for _ in 0..jobs.min(tasks.len()) {
+            let sender = sender.clone();
+            let next_task = &next_task;
+            let work = &work;
+            scope.spawn(move || loop {
+                let index = next_task.fetch_add(1, Ordering::Relaxed);
+                if index >= tasks.len() || sender.send((index, work(&tasks[index]))).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+
+        let mut pending = BTreeMap::new();
+        let mut next_result = 0;
+        for (index, result) in receiver {
+            pending.insert(index, result);
+            while let Some(result) = pending.remove(&next_result) {
+                if let Err(error) = consume(result) {
+                    // Let the workers stop at their next task
+                    next_task.store(tasks.len(), Ordering::Relaxed);
+                    return Err(error);
+                }
+                next_result += 1;
+            }
+        }
+        return Ok(());
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_ordered() {
+        let tasks: Vec<u64> = (0..50).collect();
+        let mut results = vec![];
+        let outcome: Result<(), ()> = map_ordered(
+            4,
+            &tasks,
+            |task| {
+                // Later tasks finish first
+                thread::sleep(std::time::Duration::from_micros(50 - task));
+                task * 2
+            },
+            |result| {
+                results.push(result);
+                Ok(())
+            },
+        );
+        assert_eq!(outcome, Ok(()));
+        assert_eq!(results, (0..50).map(|task| task * 2).collect::<Vec<u64>>());
+    }
+}
@@ -0,0 +1,78 @@
+use crate::day1;
+use crate::day12;
+use crate::day15;
+use crate::day16;
+use crate::day18;
+use crate::day2;
+use crate::day22;
+use crate::day23;
+use crate::day23_2;
+use crate::solver::Puzzle;
+
+pub struct DayEntry {
+    pub day: u8,
+    pub name: &'static str,
+    pub puzzle: &'static dyn Puzzle,
+}
+
+static DAYS: [DayEntry; 9] = [
+    DayEntry {
+        day: 1,
+        name: "day1",
+        puzzle: &day1::Day1,
+    },
+    DayEntry {
+        day: 2,
+        name: "day2",
+        puzzle: &day2::Day2,
+    },
+    DayEntry {
+        day: 12,
+        name: "day12",
+        puzzle: &day12::Day12,
+    },
+    DayEntry {
+        day: 15,
+        name: "day15",
+        puzzle: &day15::Day15,
+    },
+    DayEntry {
+        day: 16,
+        name: "day16",
+        puzzle: &day16::Day16,
+    },
+    DayEntry {
+        day: 18,
+        name: "day18",
+        puzzle: &day18::Day18,
+    },
+    DayEntry {
+        day: 22,
+        name: "day22",
+        puzzle: &day22::Day22,
+    },
+    DayEntry {
+        day: 23,
+        name: "day23",
+        puzzle: &day23::Day23,
+    },
+    DayEntry {
+        day: 23,
+        name: "day23_2",
+        puzzle: &day23_2::Day23V2,
+    },
+];
+
+pub fn all_days() -> &'static [DayEntry] {
+    return &DAYS;
+}
+
+pub fn find_day(day: u8) -> Vec<&'static DayEntry> {
+    return DAYS.iter().filter(|entry| entry.day == day).collect();
+}
+
+pub fn available_days() -> Vec<u8> {
+    let mut days: Vec<u8> = DAYS.iter().map(|entry| entry.day).collect();
+    days.dedup();
+    return days;
+}
@@ -0,0 +1,237 @@
+use crate::expected::Status;
+use crate::solver::Answer;
+use crate::utils::{Dataset, Part};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Text,
+    Json,
+    Csv,
+}
+
+/// Outcome of solving a part on a dataset, with its timings in µs.
+#[derive(Debug)]
+pub struct RunRecord {
+    pub day: u8,
+    pub solver: &'static str,
+    pub part: Part,
+    pub dataset: Dataset,
+    pub answer: Option<Answer>,
+    pub status: Status,
+    pub parse_micros: u128,
+    pub duration_micros: u128,
+    pub peak_memory_bytes: usize,
+}
+
+const CSV_HEADER: &str =
+    "day,solver,part,dataset,answer,status,detail,parse_us,duration_us,peak_memory_bytes";
+
+fn status_label(status: &Status) -> &'static str {
+    return match status {
+        Status::Pass => "PASS",
+        Status::Fail { .. } => "FAIL",
+        Status::Unknown => "UNKNOWN",
+        Status::Error(_) => "ERROR",
+    };
+}
+
+fn status_detail(status: &Status) -> Option<&String> {
+    return match status {
+        Status::Fail { expected } => Some(expected),
+        Status::Error(message) => Some(message),
+        _ => None,
+    };
+}
+
+fn json_string(value: &str) -> String {
+    let mut result = String::with_capacity(value.len() + 2);
+    result.push('"');
+    for chr in value.chars() {
+        match chr {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            c if (c as u32) < 0x20 => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    return result;
+}
+
+fn json_answer(answer: &Option<Answer>) -> String {
+    return match answer {
+        None => String::from("null"),
+        Some(Answer::Unsigned(value)) => value.to_string(),
+        Some(Answer::Signed(value)) => value.to_string(),
+        Some(Answer::Text(value)) => json_string(value),
+    };
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        return format!("\"{}\"", value.replace('"', "\"\""));
+    }
+    return String::from(value);
+}
+
+/// Record as a single line JSON object, holding the error or the expected answer on failure.
+pub fn to_json_line(record: &RunRecord) -> String {
+    let detail_key = if let Status::Error(_) = record.status {
+        "error"
+    } else {
+        "expected"
+    };
+    return format!(
+        "{{\"day\":{},\"solver\":{},\"part\":{},\"dataset\":{},\"answer\":{},\"status\":{},\"{}\":{},\"parse_us\":{},\"duration_us\":{},\"peak_memory_bytes\":{}}}",
+        record.day,
+        json_string(record.solver),
+        json_string(&format!("{:?}", record.part)),
+        json_string(&record.dataset.to_string()),
+        json_answer(&record.answer),
+        json_string(status_label(&record.status)),
+        detail_key,
+        status_detail(&record.status)
+            .map(|detail| json_string(detail))
+            .unwrap_or_else(|| String::from("null")),
+        record.parse_micros,
+        record.duration_micros,
+        record.peak_memory_bytes
+    );
+}
+
+/// Record as a CSV row, in the columns of the header written by [`Reporter`].
+pub fn to_csv_line(record: &RunRecord) -> String {
+    return [
+        record.day.to_string(),
+        String::from(record.solver),
+        format!("{:?}", record.part),
+        record.dataset.to_string(),
+        record
+            .answer
+            .as_ref()
+            .map(|answer| answer.to_string())
+            .unwrap_or_default(),
+        String::from(status_label(&record.status)),
+        status_detail(&record.status).cloned().unwrap_or_default(),
+        record.parse_micros.to_string(),
+        record.duration_micros.to_string(),
+        record.peak_memory_bytes.to_string(),
+    ]
+    .iter()
+    .map(|field| csv_field(field))
+    .collect::<Vec<String>>()
+    .join(",");
+}
+
+/// Human readable line, prefixed with the day, solver, part and dataset.
+pub fn to_text(record: &RunRecord) -> String {
+    // Days with several solvers report one line per solver
+    let prefix = format!(
+        "[Day {}][{}][{:?}][{}]",
+        record.day, record.solver, record.part, record.dataset
+    );
+    return match &record.answer {
+        Some(answer) => format!(
+            "{} Result {} in {} µs (parse {} µs), peak memory {} bytes: {}",
+            prefix,
+            answer,
+            record.duration_micros,
+            record.parse_micros,
+            record.peak_memory_bytes,
+            record.status
+        ),
+        None => format!("{} {}", prefix, record.status),
+    };
+}
+
+/// Writes one line per record, to stdout or to a file.
+pub struct Reporter {
+    format: ReportFormat,
+    out: Box<dyn Write>,
+}
+
+impl Reporter {
+    /// Creates the output file when there is one, and writes the CSV header.
+    pub fn new(format: ReportFormat, output: &Option<String>) -> io::Result<Reporter> {
+        let out: Box<dyn Write> = match output {
+            Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+            None => Box::new(io::stdout()),
+        };
+        let mut reporter = Reporter { format, out };
+        if let ReportFormat::Csv = format {
+            writeln!(reporter.out, "{}", CSV_HEADER)?;
+        }
+        return Ok(reporter);
+    }
+
+    /// Writes the record and flushes it, so that lines show up as days are solved.
+    pub fn record(&mut self, record: &RunRecord) -> io::Result<()> {
+        let line = match self.format {
+            ReportFormat::Text => to_text(record),
+            ReportFormat::Json => to_json_line(record),
+            ReportFormat::Csv => to_csv_line(record),
+        };
+        writeln!(self.out, "{}", line)?;
+        return self.out.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(status: Status) -> RunRecord {
+        RunRecord {
+            day: 16,
+            solver: "day16",
+            part: Part::Part2,
+            dataset: Dataset::Real,
+            answer: Some(Answer::Unsigned(2021)),
+            status,
+            parse_micros: 7,
+            duration_micros: 42,
+            peak_memory_bytes: 1024,
+        }
+    }
+
+    #[test]
+    fn test_json_line() {
+        assert_eq!(
+            to_json_line(&record(Status::Fail {
+                expected: String::from("2022")
+            })),
+            "{\"day\":16,\"solver\":\"day16\",\"part\":\"Part2\",\"dataset\":\"Real\",\"answer\":2021,\"status\":\"FAIL\",\"expected\":\"2022\",\"parse_us\":7,\"duration_us\":42,\"peak_memory_bytes\":1024}"
+        );
+    }
+
+    #[test]
+    fn test_json_line_error() {
+        let mut error_record = record(Status::Error(String::from("Cannot read \"x\"")));
+        error_record.answer = None;
+        assert_eq!(
+            to_json_line(&error_record),
+            "{\"day\":16,\"solver\":\"day16\",\"part\":\"Part2\",\"dataset\":\"Real\",\"answer\":null,\"status\":\"ERROR\",\"error\":\"Cannot read \\\"x\\\"\",\"parse_us\":7,\"duration_us\":42,\"peak_memory_bytes\":1024}"
+        );
+    }
+
+    #[test]
+    fn test_csv_line() {
+        assert_eq!(
+            to_csv_line(&record(Status::Error(String::from("bad, really")))),
+            "16,day16,Part2,Real,2021,ERROR,\"bad, really\",7,42,1024"
+        );
+    }
+
+    #[test]
+    fn test_text_line() {
+        assert_eq!(
+            to_text(&record(Status::Pass)),
+            "[Day 16][day16][Part2][Real] Result 2021 in 42 µs (parse 7 µs), peak memory 1024 bytes: PASS"
+        );
+    }
+}
@@ -0,0 +1,169 @@
+use crate::bench::{self, Samples};
+use crate::error::AocError;
+use crate::input::Input;
+use crate::utils::Part;
+use std::any::Any;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Answer {
+    Unsigned(u64),
+    Signed(i64),
+    Text(String),
+}
+
+impl fmt::Display for Answer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Answer::Unsigned(value) => write!(f, "{}", value),
+            Answer::Signed(value) => write!(f, "{}", value),
+            Answer::Text(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+impl From<u16> for Answer {
+    fn from(value: u16) -> Self {
+        Answer::Unsigned(value as u64)
+    }
+}
+
+impl From<u32> for Answer {
+    fn from(value: u32) -> Self {
+        Answer::Unsigned(value as u64)
+    }
+}
+
+impl From<u64> for Answer {
+    fn from(value: u64) -> Self {
+        Answer::Unsigned(value)
+    }
+}
+
+impl From<usize> for Answer {
+    fn from(value: usize) -> Self {
+        Answer::Unsigned(value as u64)
+    }
+}
+
+impl From<i32> for Answer {
+    fn from(value: i32) -> Self {
+        Answer::Signed(value as i64)
+    }
+}
+
+impl From<i64> for Answer {
+    fn from(value: i64) -> Self {
+        Answer::Signed(value)
+    }
+}
+
+impl From<String> for Answer {
+    fn from(value: String) -> Self {
+        Answer::Text(value)
+    }
+}
+
+/// A day puzzle: the input is parsed once into a model shared by both parts.
+pub trait Solver {
+    type Input;
+    type Output1: Into<Answer>;
+    type Output2: Into<Answer>;
+
+    fn parse(&self, input: &str) -> Result<Self::Input, AocError>;
+    fn part1(&self, input: &Self::Input) -> Result<Self::Output1, AocError>;
+    fn part2(&self, input: &Self::Input) -> Result<Self::Output2, AocError>;
+
+    /// Whether the day implements the `stream_*` methods.
+    const STREAMING: bool = false;
+
+    /// Solves part 1 straight from the lines, without building the model, when the day can do
+    /// it in constant memory.
+    fn stream_part1(&self, _input: Input) -> Option<Result<Self::Output1, AocError>> {
+        return None;
+    }
+
+    /// Same as [`Solver::stream_part1`] for part 2.
+    fn stream_part2(&self, _input: Input) -> Option<Result<Self::Output2, AocError>> {
+        return None;
+    }
+
+    /// Solves both parts in a single pass, for inputs that can only be read once.
+    #[allow(clippy::type_complexity)]
+    fn stream_parts(
+        &self,
+        _input: Input,
+    ) -> Option<Result<(Self::Output1, Self::Output2), AocError>> {
+        return None;
+    }
+}
+
+/// Parsed input of a [`Solver`], only meaningful to the puzzle that produced it.
+pub type Model = Box<dyn Any>;
+
+/// Type-erased view of a [`Solver`] so that days with different models can share a registry.
+pub trait Puzzle: Sync {
+    fn parse(&self, input: &str) -> Result<Model, AocError>;
+    fn solve(&self, part: &Part, model: &Model) -> Result<Answer, AocError>;
+    fn solve_stream(&self, part: &Part, input: Input) -> Option<Result<Answer, AocError>>;
+    fn streams(&self) -> bool;
+    fn solve_stream_parts(&self, input: Input) -> Option<Result<(Answer, Answer), AocError>>;
+    fn bench(
+        &self,
+        part: &Part,
+        input: &str,
+        warmup: usize,
+        iterations: usize,
+    ) -> Result<Samples, AocError>;
+}
+
+impl<S> Puzzle for S
+where
+    S: Solver + Sync,
+    S::Input: 'static,
+{
+    fn parse(&self, input: &str) -> Result<Model, AocError> {
+        return Solver::parse(self, input).map(|input| Box::new(input) as Model);
+    }
+
+    fn solve(&self, part: &Part, model: &Model) -> Result<Answer, AocError> {
+        let input = model
+            .downcast_ref::<S::Input>()
+            .expect("model parsed by another puzzle");
+        return match part {
+            Part::Part1 => self.part1(input).map(|answer| answer.into()),
+            Part::Part2 => self.part2(input).map(|answer| answer.into()),
+        };
+    }
+
+    fn solve_stream(&self, part: &Part, input: Input) -> Option<Result<Answer, AocError>> {
+        return match part {
+            Part::Part1 => self
+                .stream_part1(input)
+                .map(|result| result.map(|answer| answer.into())),
+            Part::Part2 => self
+                .stream_part2(input)
+                .map(|result| result.map(|answer| answer.into())),
+        };
+    }
+
+    fn streams(&self) -> bool {
+        return S::STREAMING;
+    }
+
+    fn solve_stream_parts(&self, input: Input) -> Option<Result<(Answer, Answer), AocError>> {
+        return self
+            .stream_parts(input)
+            .map(|result| result.map(|(part1, part2)| (part1.into(), part2.into())));
+    }
+
+    fn bench(
+        &self,
+        part: &Part,
+        input: &str,
+        warmup: usize,
+        iterations: usize,
+    ) -> Result<Samples, AocError> {
+        return bench::sample(self, part, input, warmup, iterations);
+    }
+}
@@ -1,87 +1,323 @@
-use std::fs::File;
-use std::io::{self, BufRead, BufReader, Error, Lines};
-use std::path::Path;
+use crate::bench::{compute_stats, BenchResult};
+use crate::error::AocError;
+use crate::expected::{ExpectedAnswers, Status};
+use crate::input::InputResolver;
+use crate::memory;
+use crate::registry::DayEntry;
+use crate::report::RunRecord;
+use crate::solver::{Answer, Model};
+use std::fmt;
 use std::time::Instant;
 
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Part {
+    Part1,
+    Part2,
+}
 
-fn read_lines_internal<P>(filename: P) -> Result<Lines<BufReader<File>>, Error>
-where
-    P: AsRef<Path>,
-{
-    let file = File::open(filename)?;
-    Ok(io::BufReader::new(file).lines())
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Dataset {
+    Test,
+    Real,
+    /// Extra file next to the standard ones, e.g. `big` for `day_15_big.txt`.
+    Named(String),
+    /// Every example of the day, see [`Dataset::Example`].
+    Examples,
+    /// File under `day_<N>/examples`, possibly with a different input per part.
+    Example(String),
+    Stdin,
+    Inline(String),
 }
 
-pub fn read_lines(day: &u8, is_test: &Dataset) -> Option<Lines<BufReader<File>>> {
-    let f = read_lines_internal(format!(
-        "./data/day_{}{}.txt",
-        day,
-        match is_test {
-            Dataset::Test => "_test",
-            _ => "",
+impl fmt::Display for Dataset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Dataset::Test => write!(f, "Test"),
+            Dataset::Real => write!(f, "Real"),
+            Dataset::Named(name) => write!(f, "{}", name),
+            Dataset::Examples => write!(f, "examples"),
+            Dataset::Example(name) => write!(f, "example:{}", name),
+            Dataset::Stdin => write!(f, "Stdin"),
+            Dataset::Inline(_) => write!(f, "Inline"),
         }
-    ));
+    }
+}
 
-    return match f {
-        Ok(lines) => Some(lines),
-        Err(_) => {
-            println!("No file found");
-            None
+/// Solves a part from its parsed input and checks the answer against the expected one.
+pub fn run(
+    entry: &DayEntry,
+    part: &Part,
+    data_set: &Dataset,
+    parsed: &Parsed,
+    expected: &ExpectedAnswers,
+) -> RunRecord {
+    let memory_baseline = memory::reset_peak();
+    let start = Instant::now();
+    let result = parsed
+        .model
+        .as_ref()
+        .map_err(|error| error.to_string())
+        .and_then(|model| {
+            entry
+                .puzzle
+                .solve(part, model)
+                .map_err(|error| error.in_file(&parsed.location).to_string())
+        });
+    let duration_micros = start.elapsed().as_micros();
+    // The model stays allocated while solving, so it counts toward the solve peak too
+    let peak_memory_bytes = parsed
+        .peak_memory_bytes
+        .max(memory::peak_since(memory_baseline) + parsed.model_bytes);
+    let (answer, status) = check(part, result, expected);
+    return RunRecord {
+        day: entry.day,
+        solver: entry.name,
+        part: *part,
+        dataset: data_set.clone(),
+        answer,
+        status,
+        parse_micros: parsed.duration_micros,
+        duration_micros,
+        peak_memory_bytes,
+    };
+}
+
+fn check(
+    part: &Part,
+    result: Result<Answer, String>,
+    expected: &ExpectedAnswers,
+) -> (Option<Answer>, Status) {
+    return match result {
+        Ok(answer) => {
+            let status = expected.check(part, &answer);
+            (Some(answer), status)
         }
+        Err(message) => (None, Status::Error(message)),
     };
 }
 
-#[derive(Debug)]
-pub enum Part {
-    Part1,
-    Part2,
+// Solves straight from the input stream when the day supports it, `None` otherwise
+fn run_stream(
+    entry: &DayEntry,
+    inputs: &InputResolver,
+    part: &Part,
+    data_set: &Dataset,
+    expected: &ExpectedAnswers,
+) -> Option<RunRecord> {
+    let location = inputs.location(&entry.day, data_set, part);
+    let memory_baseline = memory::reset_peak();
+    let start = Instant::now();
+    let result = match inputs.open(&entry.day, data_set, part) {
+        Ok(input) => entry.puzzle.solve_stream(part, input)?,
+        Err(error) => Err(error),
+    };
+    let duration_micros = start.elapsed().as_micros();
+    let peak_memory_bytes = memory::peak_since(memory_baseline);
+    let result = result.map_err(|error| error.in_file(&location).to_string());
+    let (answer, status) = check(part, result, expected);
+    return Some(RunRecord {
+        day: entry.day,
+        solver: entry.name,
+        part: *part,
+        dataset: data_set.clone(),
+        answer,
+        status,
+        parse_micros: 0,
+        duration_micros,
+        peak_memory_bytes,
+    });
 }
 
-#[derive(Debug)]
-pub enum Dataset {
-    Test,
-    Real,
+// Stdin can only be read once, so a streaming day solves every part in the same pass and each
+// part reports the duration of the whole pass
+fn run_stream_parts(
+    entry: &DayEntry,
+    inputs: &InputResolver,
+    parts: &[Part],
+    data_set: &Dataset,
+    expected: &ExpectedAnswers,
+) -> Option<Vec<RunRecord>> {
+    let location = inputs.location(&entry.day, data_set, &Part::Part1);
+    let memory_baseline = memory::reset_peak();
+    let start = Instant::now();
+    let result = match inputs.open(&entry.day, data_set, &Part::Part1) {
+        Ok(input) => entry.puzzle.solve_stream_parts(input)?,
+        Err(error) => Err(error),
+    };
+    let duration_micros = start.elapsed().as_micros();
+    let peak_memory_bytes = memory::peak_since(memory_baseline);
+    let result = result.map_err(|error| error.in_file(&location).to_string());
+    return Some(
+        parts
+            .iter()
+            .map(|part| {
+                let answer = result.clone().map(|(part1, part2)| match part {
+                    Part::Part1 => part1,
+                    Part::Part2 => part2,
+                });
+                let (answer, status) = check(part, answer, expected);
+                RunRecord {
+                    day: entry.day,
+                    solver: entry.name,
+                    part: *part,
+                    dataset: data_set.clone(),
+                    answer,
+                    status,
+                    parse_micros: 0,
+                    duration_micros,
+                    peak_memory_bytes,
+                }
+            })
+            .collect(),
+    );
 }
 
-#[allow(dead_code)]
-pub enum Active {
-    True,
-    False,
+/// Model parsed from an input, or why it could not be, with the cost of parsing.
+pub struct Parsed {
+    location: String,
+    model: Result<Model, AocError>,
+    duration_micros: u128,
+    peak_memory_bytes: usize,
+    model_bytes: usize,
 }
 
-pub fn run<F: Fn(&Part, &Vec<String>)>(day: &u8,fct: &F,part: &Part,data_set: &Dataset,lines: &Vec<String>) {
+/// Parses an input, measuring its duration and memory.
+pub fn parse(entry: &DayEntry, location: String, input: &str) -> Parsed {
+    let memory_baseline = memory::reset_peak();
     let start = Instant::now();
-    println!("[Day {}][{:?}][{:?}] Starting ", day, part, data_set);
-    fct(part, lines);
-    println!(
-        "[Day {}][{:?}][{:?}] Duration {} ms ",
-        day,
-        part,
-        data_set,
-        start.elapsed().as_millis()
-    )
+    let model = entry.puzzle.parse(input);
+    let duration_micros = start.elapsed().as_micros();
+    let peak_memory_bytes = memory::peak_since(memory_baseline);
+    let model_bytes = (memory::reset_peak() - memory_baseline).max(0) as usize;
+    return Parsed {
+        model: model.map_err(|error| error.in_file(&location)),
+        location,
+        duration_micros,
+        peak_memory_bytes,
+        model_bytes,
+    };
 }
 
-pub fn to_lines(day: &u8, data_set: &Dataset) -> Vec<String> {
-    return read_lines(day, data_set)
-        .map(|lines| lines.filter_map(|l| l.ok()).collect())
-        .unwrap_or(vec![]);
+fn load(entry: &DayEntry, inputs: &InputResolver, data_set: &Dataset, part: &Part) -> Parsed {
+    let location = inputs.location(&entry.day, data_set, part);
+    return match inputs.read(&entry.day, data_set, part) {
+        Ok(input) => parse(entry, location, &input),
+        Err(error) => Parsed {
+            location,
+            model: Err(error),
+            duration_micros: 0,
+            peak_memory_bytes: 0,
+            model_bytes: 0,
+        },
+    };
 }
 
-pub fn run_all<F: Fn(&Part, &Vec<String>)>(day: &u8, fct: &F, active: &Active) {
-    if let Active::False = active {
-        return;
+/// Runs the parts on a dataset, streaming the input when asked and the day supports it.
+/// Each input is only parsed once, and a missing example gives an error record per part.
+pub fn run_dataset(
+    entry: &DayEntry,
+    inputs: &InputResolver,
+    parts: &[Part],
+    data_set: &Dataset,
+    stream: bool,
+) -> Vec<RunRecord> {
+    let mut records = vec![];
+    let expected = inputs.expected(&entry.day, data_set);
+    // Each input is parsed once, parts sharing it reuse the model
+    let mut parsed: Vec<Parsed> = vec![];
+    if let Some(error) = inputs.missing(&entry.day, data_set) {
+        let message = error.to_string();
+        return parts
+            .iter()
+            .map(|part| RunRecord {
+                day: entry.day,
+                solver: entry.name,
+                part: *part,
+                dataset: data_set.clone(),
+                answer: None,
+                status: Status::Error(message.clone()),
+                parse_micros: 0,
+                duration_micros: 0,
+                peak_memory_bytes: 0,
+            })
+            .collect();
+    }
+    // Opening stdin consumes it, so days that cannot stream never open their input
+    let stream = stream && entry.puzzle.streams();
+    if stream && *data_set == Dataset::Stdin {
+        if let Some(records) = run_stream_parts(entry, inputs, parts, data_set, &expected) {
+            return records;
+        }
     }
+    for part in parts {
+        if !inputs.has_input(&entry.day, data_set, part) {
+            continue;
+        }
+        if stream {
+            if let Some(record) = run_stream(entry, inputs, part, data_set, &expected) {
+                records.push(record);
+                continue;
+            }
+        }
+        let location = inputs.location(&entry.day, data_set, part);
+        let index = match parsed.iter().position(|it| it.location == location) {
+            Some(index) => index,
+            None => {
+                parsed.push(load(entry, inputs, data_set, part));
+                parsed.len() - 1
+            }
+        };
+        records.push(run(entry, part, data_set, &parsed[index], &expected));
+    }
+    return records;
+}
 
-    let test_lines = to_lines(day, &Dataset::Test);
-    let real_lines = to_lines(day, &Dataset::Real);
-    run(day, fct, &Part::Part1, &Dataset::Test, &test_lines);
-    println!("");
-    run(day, fct, &Part::Part1, &Dataset::Real, &real_lines);
-    println!("");
-    run(day, fct, &Part::Part2, &Dataset::Test, &test_lines);
-    println!("");
-    run(day, fct, &Part::Part2, &Dataset::Real, &real_lines);
+/// Orders records part by part, as the datasets of a day are usually compared within a part.
+pub fn sort_by_part(records: &mut [RunRecord], parts: &[Part]) {
+    records.sort_by_key(|record| parts.iter().position(|part| *part == record.part));
+}
+
+/// Benchmarks the parts on every dataset, expanding examples. Missing examples and inputs
+/// that fail to parse or solve give an error instead of a result.
+pub fn bench_all(
+    entry: &DayEntry,
+    inputs: &InputResolver,
+    parts: &[Part],
+    data_sets: &[Dataset],
+    warmup: usize,
+    iterations: usize,
+) -> Vec<Result<BenchResult, AocError>> {
+    let mut results = vec![];
+    for data_set in inputs.expand(&entry.day, data_sets) {
+        if let Some(error) = inputs.missing(&entry.day, &data_set) {
+            results.push(Err(error));
+            continue;
+        }
+        for part in parts {
+            if !inputs.has_input(&entry.day, &data_set, part) {
+                continue;
+            }
+            let location = inputs.location(&entry.day, &data_set, part);
+            let result = inputs
+                .read(&entry.day, &data_set, part)
+                .and_then(|input| {
+                    entry
+                        .puzzle
+                        .bench(part, &input, warmup, iterations)
+                        .map_err(|error| error.in_file(&location))
+                })
+                .map(|samples| BenchResult {
+                    day: entry.day,
+                    solver: entry.name,
+                    part: *part,
+                    dataset: data_set.clone(),
+                    parse: compute_stats(&samples.parse),
+                    solve: compute_stats(&samples.solve),
+                });
+            results.push(result);
+        }
+    }
+    return results;
 }
 
 #[allow(dead_code)]